@@ -176,6 +176,190 @@ pub fn verify_collection<'a, 'b, 'c, 'info>(
     solana_program::program::invoke_signed(&ix, &ixs, ctx.signer_seeds).map_err(Into::into)
 }
 
+pub fn verify_sized_collection_item<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, VerifySizedCollectionItem<'info>>,
+) -> Result<()> {
+    let ix = mpl_token_metadata::instruction::verify_sized_collection_item(
+        ID,
+        *ctx.accounts.metadata.key,
+        *ctx.accounts.collection_authority.key,
+        *ctx.accounts.payer.key,
+        *ctx.accounts.collection_mint.key,
+        *ctx.accounts.collection.key,
+        *ctx.accounts.collection_master_edition_account.key,
+        if ctx.remaining_accounts.is_empty() {
+            None
+        } else {
+            Some(*ctx.remaining_accounts[0].key)
+        },
+    );
+    let mut ixs = vec![
+        ctx.accounts.metadata.clone(),
+        ctx.accounts.collection_authority.clone(),
+        ctx.accounts.payer.clone(),
+        ctx.accounts.collection_mint.clone(),
+        ctx.accounts.collection.clone(),
+        ctx.accounts.collection_master_edition_account.clone(),
+    ];
+    if !ctx.remaining_accounts.is_empty() {
+        ixs.push(ctx.remaining_accounts[0].clone());
+    }
+    solana_program::program::invoke_signed(&ix, &ixs, ctx.signer_seeds).map_err(Into::into)
+}
+
+pub fn unverify_collection<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, UnverifyCollection<'info>>,
+) -> Result<()> {
+    let ix = mpl_token_metadata::instruction::unverify_collection(
+        ID,
+        *ctx.accounts.metadata.key,
+        *ctx.accounts.collection_authority.key,
+        *ctx.accounts.collection_mint.key,
+        *ctx.accounts.collection.key,
+        *ctx.accounts.collection_master_edition_account.key,
+        if ctx.remaining_accounts.is_empty() {
+            None
+        } else {
+            Some(*ctx.remaining_accounts[0].key)
+        },
+    );
+    let mut ixs = vec![
+        ctx.accounts.metadata.clone(),
+        ctx.accounts.collection_authority.clone(),
+        ctx.accounts.collection_mint.clone(),
+        ctx.accounts.collection.clone(),
+        ctx.accounts.collection_master_edition_account.clone(),
+    ];
+    if !ctx.remaining_accounts.is_empty() {
+        ixs.push(ctx.remaining_accounts[0].clone());
+    }
+    solana_program::program::invoke_signed(&ix, &ixs, ctx.signer_seeds).map_err(Into::into)
+}
+
+pub fn unverify_sized_collection_item<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, UnverifySizedCollectionItem<'info>>,
+) -> Result<()> {
+    let ix = mpl_token_metadata::instruction::unverify_sized_collection_item(
+        ID,
+        *ctx.accounts.metadata.key,
+        *ctx.accounts.collection_authority.key,
+        *ctx.accounts.payer.key,
+        *ctx.accounts.collection_mint.key,
+        *ctx.accounts.collection.key,
+        *ctx.accounts.collection_master_edition_account.key,
+        if ctx.remaining_accounts.is_empty() {
+            None
+        } else {
+            Some(*ctx.remaining_accounts[0].key)
+        },
+    );
+    let mut ixs = vec![
+        ctx.accounts.metadata.clone(),
+        ctx.accounts.collection_authority.clone(),
+        ctx.accounts.payer.clone(),
+        ctx.accounts.collection_mint.clone(),
+        ctx.accounts.collection.clone(),
+        ctx.accounts.collection_master_edition_account.clone(),
+    ];
+    if !ctx.remaining_accounts.is_empty() {
+        ixs.push(ctx.remaining_accounts[0].clone());
+    }
+    solana_program::program::invoke_signed(&ix, &ixs, ctx.signer_seeds).map_err(Into::into)
+}
+
+pub fn set_and_verify_collection<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, SetAndVerifyCollection<'info>>,
+) -> Result<()> {
+    let ix = mpl_token_metadata::instruction::set_and_verify_collection(
+        ID,
+        *ctx.accounts.metadata.key,
+        *ctx.accounts.collection_authority.key,
+        *ctx.accounts.payer.key,
+        *ctx.accounts.update_authority.key,
+        *ctx.accounts.collection_mint.key,
+        *ctx.accounts.collection.key,
+        *ctx.accounts.collection_master_edition_account.key,
+        if ctx.remaining_accounts.is_empty() {
+            None
+        } else {
+            Some(*ctx.remaining_accounts[0].key)
+        },
+    );
+    let mut ixs = vec![
+        ctx.accounts.metadata.clone(),
+        ctx.accounts.collection_authority.clone(),
+        ctx.accounts.payer.clone(),
+        ctx.accounts.update_authority.clone(),
+        ctx.accounts.collection_mint.clone(),
+        ctx.accounts.collection.clone(),
+        ctx.accounts.collection_master_edition_account.clone(),
+    ];
+    if !ctx.remaining_accounts.is_empty() {
+        ixs.push(ctx.remaining_accounts[0].clone());
+    }
+    solana_program::program::invoke_signed(&ix, &ixs, ctx.signer_seeds).map_err(Into::into)
+}
+
+pub fn set_and_verify_sized_collection_item<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, SetAndVerifySizedCollectionItem<'info>>,
+) -> Result<()> {
+    let ix = mpl_token_metadata::instruction::set_and_verify_sized_collection_item(
+        ID,
+        *ctx.accounts.metadata.key,
+        *ctx.accounts.collection_authority.key,
+        *ctx.accounts.payer.key,
+        *ctx.accounts.update_authority.key,
+        *ctx.accounts.collection_mint.key,
+        *ctx.accounts.collection.key,
+        *ctx.accounts.collection_master_edition_account.key,
+        if ctx.remaining_accounts.is_empty() {
+            None
+        } else {
+            Some(*ctx.remaining_accounts[0].key)
+        },
+    );
+    let mut ixs = vec![
+        ctx.accounts.metadata.clone(),
+        ctx.accounts.collection_authority.clone(),
+        ctx.accounts.payer.clone(),
+        ctx.accounts.update_authority.clone(),
+        ctx.accounts.collection_mint.clone(),
+        ctx.accounts.collection.clone(),
+        ctx.accounts.collection_master_edition_account.clone(),
+    ];
+    if !ctx.remaining_accounts.is_empty() {
+        ixs.push(ctx.remaining_accounts[0].clone());
+    }
+    solana_program::program::invoke_signed(&ix, &ixs, ctx.signer_seeds).map_err(Into::into)
+}
+
+pub fn set_collection_size<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, SetCollectionSize<'info>>,
+    size: u64,
+) -> Result<()> {
+    let ix = mpl_token_metadata::instruction::set_collection_size(
+        ID,
+        *ctx.accounts.metadata.key,
+        *ctx.accounts.update_authority.key,
+        *ctx.accounts.mint.key,
+        if ctx.remaining_accounts.is_empty() {
+            None
+        } else {
+            Some(*ctx.remaining_accounts[0].key)
+        },
+        size,
+    );
+    let mut ixs = vec![
+        ctx.accounts.metadata.clone(),
+        ctx.accounts.update_authority.clone(),
+        ctx.accounts.mint.clone(),
+    ];
+    if !ctx.remaining_accounts.is_empty() {
+        ixs.push(ctx.remaining_accounts[0].clone());
+    }
+    solana_program::program::invoke_signed(&ix, &ixs, ctx.signer_seeds).map_err(Into::into)
+}
+
 pub fn approve_collection_authority<'a, 'b, 'c, 'info>(
     ctx: CpiContext<'a, 'b, 'c, 'info, ApproveCollectionAuthority<'info>>,
 ) -> Result<()> {
@@ -206,6 +390,37 @@ pub fn approve_collection_authority<'a, 'b, 'c, 'info>(
     .map_err(Into::into)
 }
 
+pub fn burn_nft<'a, 'b, 'c, 'info>(
+    ctx: CpiContext<'a, 'b, 'c, 'info, BurnNft<'info>>,
+) -> Result<()> {
+    let ix = mpl_token_metadata::instruction::burn_nft(
+        ID,
+        *ctx.accounts.metadata.key,
+        *ctx.accounts.owner.key,
+        *ctx.accounts.mint.key,
+        *ctx.accounts.token.key,
+        *ctx.accounts.edition.key,
+        *ctx.accounts.spl_token.key,
+        if ctx.remaining_accounts.is_empty() {
+            None
+        } else {
+            Some(*ctx.remaining_accounts[0].key)
+        },
+    );
+    let mut ixs = vec![
+        ctx.accounts.metadata.clone(),
+        ctx.accounts.owner.clone(),
+        ctx.accounts.mint.clone(),
+        ctx.accounts.token.clone(),
+        ctx.accounts.edition.clone(),
+        ctx.accounts.spl_token.clone(),
+    ];
+    if !ctx.remaining_accounts.is_empty() {
+        ixs.push(ctx.remaining_accounts[0].clone());
+    }
+    solana_program::program::invoke_signed(&ix, &ixs, ctx.signer_seeds).map_err(Into::into)
+}
+
 #[derive(Accounts)]
 pub struct UpdatePrimarySaleHappenedViaToken<'info> {
     /// CHECK:
@@ -288,6 +503,98 @@ pub struct VerifyCollection<'info> {
     pub collection_master_edition_account: AccountInfo<'info>,
 }
 
+#[derive(Accounts)]
+pub struct VerifySizedCollectionItem<'info> {
+    /// CHECK: metadata account
+    pub metadata: AccountInfo<'info>,
+    /// CHECK: collection update authority
+    pub collection_authority: AccountInfo<'info>,
+    /// CHECK:
+    pub payer: AccountInfo<'info>,
+    /// CHECK: mint of collection
+    pub collection_mint: AccountInfo<'info>,
+    /// CHECK: metadata account of collection, mutable
+    pub collection: AccountInfo<'info>,
+    /// CHECK: master edition v2 account of collection token
+    pub collection_master_edition_account: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UnverifyCollection<'info> {
+    /// CHECK: metadata account
+    pub metadata: AccountInfo<'info>,
+    /// CHECK: collection update authority
+    pub collection_authority: AccountInfo<'info>,
+    /// CHECK: mint of collection
+    pub collection_mint: AccountInfo<'info>,
+    /// CHECK: metadata account of collection
+    pub collection: AccountInfo<'info>,
+    /// CHECK: master edition v2 account of collection token
+    pub collection_master_edition_account: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UnverifySizedCollectionItem<'info> {
+    /// CHECK: metadata account
+    pub metadata: AccountInfo<'info>,
+    /// CHECK: collection update authority
+    pub collection_authority: AccountInfo<'info>,
+    /// CHECK:
+    pub payer: AccountInfo<'info>,
+    /// CHECK: mint of collection
+    pub collection_mint: AccountInfo<'info>,
+    /// CHECK: metadata account of collection, mutable
+    pub collection: AccountInfo<'info>,
+    /// CHECK: master edition v2 account of collection token
+    pub collection_master_edition_account: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAndVerifyCollection<'info> {
+    /// CHECK: metadata account
+    pub metadata: AccountInfo<'info>,
+    /// CHECK: collection update authority
+    pub collection_authority: AccountInfo<'info>,
+    /// CHECK:
+    pub payer: AccountInfo<'info>,
+    /// CHECK: update authority of the nft
+    pub update_authority: AccountInfo<'info>,
+    /// CHECK: mint of collection
+    pub collection_mint: AccountInfo<'info>,
+    /// CHECK: metadata account of collection
+    pub collection: AccountInfo<'info>,
+    /// CHECK: master edition v2 account of collection token
+    pub collection_master_edition_account: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetAndVerifySizedCollectionItem<'info> {
+    /// CHECK: metadata account
+    pub metadata: AccountInfo<'info>,
+    /// CHECK: collection update authority
+    pub collection_authority: AccountInfo<'info>,
+    /// CHECK:
+    pub payer: AccountInfo<'info>,
+    /// CHECK: update authority of the nft
+    pub update_authority: AccountInfo<'info>,
+    /// CHECK: mint of collection
+    pub collection_mint: AccountInfo<'info>,
+    /// CHECK: metadata account of collection, mutable
+    pub collection: AccountInfo<'info>,
+    /// CHECK: master edition v2 account of collection token
+    pub collection_master_edition_account: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetCollectionSize<'info> {
+    /// CHECK: metadata account of collection, mutable
+    pub metadata: AccountInfo<'info>,
+    /// CHECK: collection update authority, signer
+    pub update_authority: AccountInfo<'info>,
+    /// CHECK: mint of collection
+    pub mint: AccountInfo<'info>,
+}
+
 #[derive(Accounts)]
 pub struct ApproveCollectionAuthority<'info> {
     /// CHECK: empty account which derived from ['metadata', metadata::ID, mint, 'collection_authority', new_collection_authority]
@@ -310,6 +617,22 @@ pub struct ApproveCollectionAuthority<'info> {
     pub rent: AccountInfo<'info>,
 }
 
+#[derive(Accounts)]
+pub struct BurnNft<'info> {
+    /// CHECK: metadata account
+    pub metadata: AccountInfo<'info>,
+    /// CHECK: owner of the token account, signer
+    pub owner: AccountInfo<'info>,
+    /// CHECK:
+    pub mint: AccountInfo<'info>,
+    /// CHECK: token account holding the nft
+    pub token: AccountInfo<'info>,
+    /// CHECK: master edition account
+    pub edition: AccountInfo<'info>,
+    /// CHECK:
+    pub spl_token: AccountInfo<'info>,
+}
+
 #[derive(Debug, Clone)]
 pub struct MetadataAccount(state::Metadata);
 
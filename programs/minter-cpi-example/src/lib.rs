@@ -0,0 +1,101 @@
+//! Example consumer of the minter CPI interface: mints NFTs into a vault PDA
+//! of this program, paid for by the user.
+
+use anchor_lang::prelude::*;
+use anchor_metadata::token_metadata::TokenMetadata;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::Token;
+use minter::{interface, program::Minter, MinterAccount};
+
+declare_id!("7jUNPZst1W7Szqw58hSXYJ81FiNQtUNDfUZnsUddyXCa");
+
+const VAULT_KEY: &[u8] = b"vault";
+
+#[program]
+pub mod minter_cpi_example {
+    use super::*;
+
+    /// Mints an NFT to the vault, paid for by the user.
+    pub fn mint_to_vault(
+        ctx: Context<MintToVault>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        interface::mint_for(
+            ctx.accounts.into_mint_for_context(),
+            ctx.accounts.minter.count,
+            name,
+            symbol,
+            uri,
+            0,
+            true,
+        )
+    }
+}
+
+#[derive(Accounts)]
+pub struct MintToVault<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: only receives the NFTs
+    #[account(seeds = [VAULT_KEY], bump)]
+    pub vault: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub minter: Account<'info, MinterAccount>,
+
+    /// CHECK: checked by the minter
+    #[account(mut)]
+    pub mint_record: UncheckedAccount<'info>,
+
+    /// CHECK: checked by the minter
+    #[account(mut)]
+    pub mint: UncheckedAccount<'info>,
+
+    /// CHECK: checked by the associated token program
+    #[account(mut)]
+    pub associated_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: checked by Token Metadata
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: checked by Token Metadata
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+
+    pub minter_program: Program<'info, Minter>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_metadata_program: Program<'info, TokenMetadata>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> MintToVault<'info> {
+    pub fn into_mint_for_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, minter::cpi::accounts::MintFor<'info>> {
+        let cpi_program = self.minter_program.to_account_info();
+        let cpi_accounts = minter::cpi::accounts::MintFor {
+            payer: self.user.to_account_info(),
+            minter: self.minter.to_account_info(),
+            mint_record: self.mint_record.to_account_info(),
+            basic: minter::cpi::accounts::BasicMint {
+                mint: self.mint.to_account_info(),
+                recipient: self.vault.to_account_info(),
+                associated_token_account: self.associated_token_account.to_account_info(),
+                metadata: self.metadata.to_account_info(),
+                master_edition: self.master_edition.to_account_info(),
+                system_program: self.system_program.to_account_info(),
+                token_program: self.token_program.to_account_info(),
+                associated_token_program: self.associated_token_program.to_account_info(),
+                token_metadata_program: self.token_metadata_program.to_account_info(),
+                rent: self.rent.to_account_info(),
+            },
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
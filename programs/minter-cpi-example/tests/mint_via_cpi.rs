@@ -0,0 +1,120 @@
+//! Mints through the example program, with the user as payer and its vault
+//! PDA as recipient.
+//!
+//! Needs the BPF builds of the minter, this program and Token Metadata, e.g.
+//! `cp metadata.so programs/minter-cpi-example/tests/fixtures/ && cargo test-bpf`.
+#![cfg(feature = "test-bpf")]
+
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anchor_metadata::token_metadata;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::TokenAccount;
+use minter::interface;
+use solana_program_test::{BanksClient, ProgramTest};
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program, sysvar,
+    transaction::Transaction,
+};
+
+fn program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::new("minter", minter::id(), None);
+    program_test.add_program("minter_cpi_example", minter_cpi_example::id(), None);
+    program_test.add_program("metadata", token_metadata::ID, None);
+    program_test
+}
+
+async fn process(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: Hash,
+    instruction: Instruction,
+) {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+}
+
+async fn account<T: AccountDeserialize>(banks_client: &mut BanksClient, address: Pubkey) -> T {
+    let account = banks_client.get_account(address).await.unwrap().unwrap();
+    T::try_deserialize(&mut account.data.as_ref()).unwrap()
+}
+
+#[tokio::test]
+async fn mint_to_vault() {
+    let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
+
+    let (minter, _) = interface::minter_address(&payer.pubkey());
+    process(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        Instruction {
+            program_id: minter::id(),
+            accounts: minter::accounts::InitializeMinter {
+                authority: payer.pubkey(),
+                minter,
+                system_program: system_program::id(),
+                rent: sysvar::rent::id(),
+            }
+            .to_account_metas(None),
+            data: minter::instruction::InitializeMinter {}.data(),
+        },
+    )
+    .await;
+
+    let (vault, _) = Pubkey::find_program_address(&[b"vault"], &minter_cpi_example::id());
+    let (mint, _) = interface::mint_address(0);
+    let (mint_record, _) = minter::MintRecord::address(&mint);
+    let vault_token_account = get_associated_token_address(&vault, &mint);
+    process(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        Instruction {
+            program_id: minter_cpi_example::id(),
+            accounts: minter_cpi_example::accounts::MintToVault {
+                user: payer.pubkey(),
+                vault,
+                minter,
+                mint_record,
+                mint,
+                associated_token_account: vault_token_account,
+                metadata: interface::metadata_address(&mint),
+                master_edition: interface::master_edition_address(&mint),
+                minter_program: minter::id(),
+                system_program: system_program::id(),
+                token_program: anchor_spl::token::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+                token_metadata_program: token_metadata::ID,
+                rent: sysvar::rent::id(),
+            }
+            .to_account_metas(None),
+            data: minter_cpi_example::instruction::MintToVault {
+                name: "Vault #0".to_string(),
+                symbol: "VAULT".to_string(),
+                uri: "https://example.com/0.json".to_string(),
+            }
+            .data(),
+        },
+    )
+    .await;
+
+    let token: TokenAccount = account(&mut banks_client, vault_token_account).await;
+    assert_eq!(token.owner, vault);
+    assert_eq!(token.amount, 1);
+
+    let record: minter::MintRecord = account(&mut banks_client, mint_record).await;
+    assert_eq!(record.minter, minter);
+    assert_eq!(record.recipient, vault);
+
+    let minter: minter::MinterAccount = account(&mut banks_client, minter).await;
+    assert_eq!(minter.count, 1);
+}
@@ -0,0 +1,141 @@
+//! Supported interface for programs minting through CPI.
+//!
+//! The payer of `mint_for` pays the mint rent and is added as a creator, the
+//! recipient receives the NFT in its associated token account. The recipient
+//! can be any account, so a program can mint to one of its PDAs with a user
+//! paying for it. A PDA payer signs through `invoke_signed` and must be a
+//! funded system account without data.
+//!
+//! The v2 instructions, `mint_with_collection` and `mint_unordered`, work the
+//! same way, except that paid mints flagging the primary sale also need the
+//! recipient to sign. Their extra accounts, which depend on the minter config,
+//! go through `CpiContext::with_remaining_accounts`.
+
+use anchor_lang::prelude::*;
+use anchor_metadata::token_metadata;
+
+use crate::{
+    mint_index_seed, MintRecord, MINTER_KEY, MINTER_KEY_V2, MINT_COUNTER_KEY, MINT_KEY, MINT_KEY_V2,
+};
+
+pub fn minter_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MINTER_KEY, owner.as_ref()], &crate::ID)
+}
+
+pub fn minter_v2_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MINTER_KEY_V2, owner.as_ref()], &crate::ID)
+}
+
+/// Address of the mint a minter issues at `count`.
+pub fn mint_address(count: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MINT_KEY, &mint_index_seed(count)], &crate::ID)
+}
+
+/// Address of the mint a v2 minter issues at `count`.
+pub fn mint_v2_address(count: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MINT_KEY_V2, &mint_index_seed(count)], &crate::ID)
+}
+
+/// Address of the `shard` counter of a minter's unordered mints.
+pub fn mint_counter_address(minter: &Pubkey, shard: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[MINT_COUNTER_KEY, minter.as_ref(), &[shard]], &crate::ID)
+}
+
+pub fn metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"metadata", token_metadata::ID.as_ref(), mint.as_ref()],
+        &token_metadata::ID,
+    )
+    .0
+}
+
+pub fn master_edition_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"metadata",
+            token_metadata::ID.as_ref(),
+            mint.as_ref(),
+            b"edition",
+        ],
+        &token_metadata::ID,
+    )
+    .0
+}
+
+/// Calls `mint_for` for the mint at `count`, the minter's current count,
+/// deriving the mint and mint record bumps.
+pub fn mint_for<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, crate::cpi::accounts::MintFor<'info>>,
+    count: u64,
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    is_mutable: bool,
+) -> Result<()> {
+    let (_, mint_bump) = mint_address(count);
+    let (_, record_bump) = MintRecord::address(ctx.accounts.basic.mint.key);
+    crate::cpi::mint_for(
+        ctx,
+        mint_bump,
+        record_bump,
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points,
+        is_mutable,
+    )
+}
+
+/// Calls `mint_with_collection` for the mint at `count`, the minter's current
+/// count, deriving the mint and mint record bumps.
+pub fn mint_with_collection<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, crate::cpi::accounts::MintWithCollection<'info>>,
+    count: u64,
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    is_mutable: bool,
+    collection: Pubkey,
+) -> Result<()> {
+    let (_, mint_bump) = mint_v2_address(count);
+    let (_, record_bump) = MintRecord::address(ctx.accounts.basic.mint.key);
+    crate::cpi::mint_with_collection(
+        ctx,
+        mint_bump,
+        record_bump,
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points,
+        is_mutable,
+        collection,
+    )
+}
+
+/// Calls `mint_unordered`, deriving the mint record bump. The mint is a fresh
+/// keypair that has to sign the transaction.
+pub fn mint_unordered<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, crate::cpi::accounts::MintUnordered<'info>>,
+    shard: u8,
+    name: String,
+    symbol: String,
+    uri: String,
+    seller_fee_basis_points: u16,
+    is_mutable: bool,
+    collection: Pubkey,
+) -> Result<()> {
+    let (_, record_bump) = MintRecord::address(ctx.accounts.basic.mint.key);
+    crate::cpi::mint_unordered(
+        ctx,
+        record_bump,
+        shard,
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points,
+        is_mutable,
+        collection,
+    )
+}
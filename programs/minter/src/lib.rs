@@ -1,13 +1,22 @@
+// the instruction handlers, and the CPI helpers anchor generates for them,
+// take the accounts context plus every instruction argument
+#![allow(clippy::too_many_arguments)]
+
 use std::ops::{Deref, DerefMut};
 
 use anchor_lang::prelude::*;
 use anchor_metadata::token_metadata::{self, TokenMetadata};
 use anchor_spl::associated_token::{self, AssociatedToken};
-use anchor_spl::token::{self, Mint, Token};
+use anchor_spl::token::{self, Mint, Token, TokenAccount};
 use solana_program::{
+    account_info::next_account_info,
+    instruction::{AccountMeta, Instruction},
+    keccak,
     program::{invoke, invoke_signed},
     system_instruction,
+    sysvar::slot_hashes,
 };
+use spl_token_2022::extension::ExtensionType;
 
 solana_security_txt::security_txt! {
     name: "Minter",
@@ -18,6 +27,9 @@ solana_security_txt::security_txt! {
 
 declare_id!("D2bEcGfANjxMf9ZxkVVvbtC6tsoo2YVfnT8nP6TTRBWJ");
 
+#[cfg(feature = "cpi")]
+pub mod interface;
+
 #[error_code]
 pub enum MinterError {
     #[msg("Invalid mint address")]
@@ -30,12 +42,175 @@ pub enum MinterError {
     InvalidMasterEditionAddress,
     #[msg("Mismatched minter owner")]
     MismatchedMinterOwner,
+    #[msg("Metadata template too long")]
+    MetadataTemplateTooLong,
+    #[msg("Client supplied metadata is not allowed by this minter")]
+    ClientMetadataForbidden,
+    #[msg("Hidden mints must be mutable")]
+    HiddenMintMustBeMutable,
+    #[msg("Index out of range")]
+    IndexOutOfRange,
+    #[msg("Index already revealed")]
+    AlreadyRevealed,
+    #[msg("Reveal items and metadata accounts do not match")]
+    MismatchedRevealAccounts,
+    #[msg("Invalid index pool")]
+    InvalidIndexPool,
+    #[msg("Index pool exhausted")]
+    IndexPoolExhausted,
+    #[msg("Invalid slot hashes sysvar")]
+    InvalidSlotHashes,
+    #[msg("Name too long")]
+    NameTooLong,
+    #[msg("Symbol too long")]
+    SymbolTooLong,
+    #[msg("Uri too long")]
+    UriTooLong,
+    #[msg("Seller fee basis points cannot exceed 10000")]
+    InvalidSellerFeeBasisPoints,
+    #[msg("Seller fee basis points exceed the minter maximum")]
+    SellerFeeBasisPointsTooHigh,
+    #[msg("Uri does not start with the allowed prefix")]
+    UriPrefixNotAllowed,
+    #[msg("NFT was not issued by this minter")]
+    NotIssuedByMinter,
+    #[msg("Collection is not bound to the minter")]
+    MismatchedCollection,
+    #[msg("Mint bumps and metadata accounts do not match")]
+    MismatchedItemAccounts,
+    #[msg("Invalid treasury account")]
+    InvalidTreasury,
+    #[msg("Invalid gate token")]
+    InvalidGateToken,
+    #[msg("Gate token is not in the required collection")]
+    GateCollectionMismatch,
+    #[msg("Gate token has reached its usage limit")]
+    GateUsageExceeded,
+    #[msg("Invalid gate usage account")]
+    InvalidGateUsage,
+    #[msg("Burned NFT is not in the required collection")]
+    BurnCollectionMismatch,
+    #[msg("Invalid burn token account")]
+    InvalidBurnToken,
+    #[msg("Minter account layout is outdated, migrate it first")]
+    OutdatedMinterLayout,
+    #[msg("Mint counter overflow")]
+    CounterOverflow,
+    #[msg("Invalid mint record address")]
+    InvalidMintRecordAddress,
+    #[msg("Mint record does not match the mint or minter")]
+    ProvenanceMismatch,
+    #[msg("Invalid auction config")]
+    InvalidAuctionConfig,
+    #[msg("Invalid auction account")]
+    InvalidAuction,
+    #[msg("Auction has not started")]
+    AuctionNotStarted,
+    #[msg("Auction is not settled")]
+    AuctionNotSettled,
+    #[msg("Invalid auction receipt")]
+    InvalidAuctionReceipt,
+    #[msg("Payment amount overflow")]
+    PaymentOverflow,
+    #[msg("Invalid phase config")]
+    InvalidPhaseConfig,
+    #[msg("Invalid phase account")]
+    InvalidPhase,
+    #[msg("Phase is not active")]
+    PhaseNotActive,
+    #[msg("Phase has already started")]
+    PhaseAlreadyStarted,
+    #[msg("Phase overlaps another phase of the minter")]
+    OverlappingPhases,
+    #[msg("Every other phase of the minter must be passed, in id order")]
+    MissingPhases,
+    #[msg("Wallet mint limit exceeded")]
+    WalletLimitExceeded,
+    #[msg("Invalid wallet mints account")]
+    InvalidWalletMints,
+    #[msg("Referral basis points cannot exceed 10000")]
+    InvalidReferralBasisPoints,
+    #[msg("Payer cannot refer themselves")]
+    SelfReferral,
+    #[msg("Invalid referrer stats account")]
+    InvalidReferrerStats,
+    #[msg("Payout shares must sum to 10000 basis points")]
+    InvalidPayouts,
+    #[msg("Too many payout recipients")]
+    TooManyPayouts,
+    #[msg("Invalid payout account")]
+    InvalidPayoutAccount,
+    #[msg("Auctions are priced in lamports only")]
+    SplPaymentUnsupported,
+    #[msg("Escrow cannot be combined with auctions or SPL payments")]
+    EscrowConflict,
+    #[msg("Referrals cannot be combined with SPL payments or escrow")]
+    ReferralConflict,
+    #[msg("Escrow cannot change while it holds escrowed mints")]
+    EscrowInUse,
+    #[msg("Invalid escrow config")]
+    InvalidEscrowConfig,
+    #[msg("Drop missed its sale threshold")]
+    DropFailed,
+    #[msg("Sale threshold not reached")]
+    EscrowThresholdNotMet,
+    #[msg("Refunds open once the deadline passes below the threshold")]
+    RefundUnavailable,
+    #[msg("Invalid vault account")]
+    InvalidVault,
+    #[msg("Invalid payment receipt")]
+    InvalidPaymentReceipt,
+    #[msg("Token-2022 minting is not enabled for this minter")]
+    Token2022Disabled,
+    #[msg("Minter config is not supported for Token-2022 mints")]
+    Token2022Unsupported,
+    #[msg("Token-2022 mints have no metadata to apply the minter's metadata settings to")]
+    Token2022MetadataUnsupported,
+    #[msg("Token-2022 minters mint through mint_token_2022 only")]
+    Token2022Enabled,
+    #[msg("Initial supply exceeds the max supply")]
+    InvalidFungibleSupply,
+    #[msg("Max supply exceeded")]
+    SupplyCapExceeded,
+    #[msg("Unordered minting is not enabled for this minter")]
+    UnorderedDisabled,
+    #[msg("Minter config is not supported for unordered mints")]
+    UnorderedUnsupported,
+    #[msg("Mint must sign unordered mints")]
+    MintNotSigner,
+    #[msg("Invalid mint counter")]
+    InvalidMintCounter,
+    #[msg("Recipient must sign paid mints that flag the primary sale")]
+    RecipientNotSigner,
 }
 
 const MINTER_KEY: &[u8] = b"minter";
 const MINTER_KEY_V2: &[u8] = b"minter_v2";
 const MINT_KEY: &[u8] = b"mint";
 const MINT_KEY_V2: &[u8] = b"mint_v2";
+const MINT_RECORD_KEY: &[u8] = b"mint_record";
+const COLLECTION_MINT_KEY: &[u8] = b"collection_mint";
+const FUNGIBLE_MINT_KEY: &[u8] = b"fungible_mint";
+const MINT_COUNTER_KEY: &[u8] = b"mint_counter";
+const MAX_BASIS_POINTS: u16 = 10000;
+const AUCTION_KEY: &[u8] = b"auction";
+const AUCTION_RECEIPT_KEY: &[u8] = b"auction_receipt";
+const PHASE_KEY: &[u8] = b"phase";
+const WALLET_MINTS_KEY: &[u8] = b"wallet_mints";
+const REFERRER_STATS_KEY: &[u8] = b"referrer_stats";
+const VAULT_KEY: &[u8] = b"vault";
+const PAYMENT_RECEIPT_KEY: &[u8] = b"payment_receipt";
+const GATE_USAGE_KEY: &[u8] = b"gate_usage";
+const REVEAL_KEY: &[u8] = b"reveal";
+const INDEX_POOL_KEY: &[u8] = b"index_pool";
+const COLLECTION_REGISTRY_KEY: &[u8] = b"collection_registry";
+const FUNGIBLE_KEY: &[u8] = b"fungible";
+const MAX_PAYOUTS: usize = 5;
+const MINTER_LAYOUT_VERSION: u8 = 1;
+const MINTER_V2_LAYOUT_VERSION: u8 = 1;
+// account sizes when layout version 1 was introduced
+const MINTER_VERSIONED_SIZE: usize = 8 + 46;
+const MINTER_V2_VERSIONED_SIZE: usize = 8 + 1125;
 
 #[program]
 pub mod minter {
@@ -44,22 +219,612 @@ pub mod minter {
     pub fn initialize_minter(ctx: Context<InitializeMinter>) -> Result<()> {
         let minter = &mut ctx.accounts.minter;
         minter.owner = *ctx.accounts.authority.key;
+        minter.bump = *ctx.bumps.get("minter").unwrap();
+        minter.version = MINTER_LAYOUT_VERSION;
         Ok(())
     }
 
     pub fn initialize_minter_v2(ctx: Context<InitializeMinterV2>) -> Result<()> {
         let minter = &mut ctx.accounts.minter;
         minter.owner = *ctx.accounts.authority.key;
+        minter.bump = *ctx.bumps.get("minter").unwrap();
+        minter.version = MINTER_V2_LAYOUT_VERSION;
+        Ok(())
+    }
+
+    /// Grows a minter to the current layout, records its bump and carries the
+    /// count over to the current counter.
+    pub fn migrate_minter(ctx: Context<MigrateMinter>) -> Result<()> {
+        let minter_info = ctx.accounts.minter.to_account_info();
+        // accounts shorter than the first versioned layout predate `version`
+        // and `count`, whatever sits at their offsets is leftover data
+        let legacy = minter_info.data_len() < MINTER_VERSIONED_SIZE;
+
+        realloc_account_raw(
+            &minter_info,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            8 + MinterAccount::LEN,
+        )?;
+
+        let mut minter = Account::<MinterAccount>::try_from(&minter_info)?;
+        minter.bump = *ctx.bumps.get("minter").unwrap();
+        if legacy {
+            minter.count = minter.legacy_count.into();
+            minter.legacy_count = 0;
+        }
+        minter.version = MINTER_LAYOUT_VERSION;
+        minter.exit(ctx.program_id)
+    }
+
+    /// Grows a v2 minter to the current layout, records its bump and carries
+    /// the count over to the current counter.
+    pub fn migrate_minter_v2(ctx: Context<MigrateMinterV2>) -> Result<()> {
+        let minter_info = ctx.accounts.minter.to_account_info();
+        // accounts shorter than the first versioned layout predate `version`
+        // and `count`, whatever sits at their offsets is leftover data
+        let legacy = minter_info.data_len() < MINTER_V2_VERSIONED_SIZE;
+
+        realloc_account_raw(
+            &minter_info,
+            &ctx.accounts.authority.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            8 + MinterAccountV2::LEN,
+        )?;
+
+        let mut minter = if legacy {
+            MinterAccountV2::deserialize_legacy(&minter_info.try_borrow_data()?)?
+        } else {
+            MinterAccountV2::try_deserialize(&mut &minter_info.try_borrow_data()?[..])?
+        };
+        minter.bump = *ctx.bumps.get("minter").unwrap();
+        if legacy {
+            minter.count = minter.legacy_count.into();
+            minter.legacy_count = 0;
+        }
+        minter.version = MINTER_V2_LAYOUT_VERSION;
+
+        // legacy bytes past `collection` mean nothing in the current layout,
+        // clear them with the rewrite
+        let mut data = minter_info.try_borrow_mut_data()?;
+        data[8..].fill(0);
+        let mut writer: &mut [u8] = &mut data[8..];
+        minter.serialize(&mut writer)?;
+        Ok(())
+    }
+
+    /// Sets the name and uri template of the minter's NFTs. It applies to
+    /// `mint_with_collection`, `mint_unordered` refuses templated minters, and
+    /// v1 minters and `mint_for` keep taking the metadata from the client as is.
+    pub fn set_metadata_template(
+        ctx: Context<SetMetadataTemplate>,
+        template: Option<MetadataTemplate>,
+    ) -> Result<()> {
+        if let Some(template) = &template {
+            template.validate()?;
+        }
+
+        let minter = &mut ctx.accounts.minter;
+        minter.template = template;
+        Ok(())
+    }
+
+    pub fn set_hidden_settings(
+        ctx: Context<SetHiddenSettings>,
+        hidden: Option<HiddenSettings>,
+    ) -> Result<()> {
+        if let Some(hidden) = &hidden {
+            hidden.validate()?;
+        }
+
+        let minter = &mut ctx.accounts.minter;
+        minter.hidden = hidden;
+        Ok(())
+    }
+
+    pub fn initialize_reveal_record(ctx: Context<InitializeRevealRecord>, size: u32) -> Result<()> {
+        let record = &mut ctx.accounts.reveal_record;
+        record.minter = ctx.accounts.minter.key();
+        record.bump = *ctx.bumps.get("reveal_record").unwrap();
+        record.size = size;
+        record.bitmap = vec![0; RevealRecord::bitmap_len(size)];
+        Ok(())
+    }
+
+    /// Sets the final name and uri of hidden NFTs. Metadata accounts are passed
+    /// as remaining accounts, in the same order as `items`.
+    pub fn reveal<'info>(
+        ctx: Context<'_, '_, '_, 'info, Reveal<'info>>,
+        items: Vec<RevealItem>,
+    ) -> Result<()> {
+        if items.len() != ctx.remaining_accounts.len() {
+            return Err(MinterError::MismatchedRevealAccounts.into());
+        }
+
+        let minter_seeds = &[
+            MINTER_KEY_V2,
+            ctx.accounts.minter.owner.as_ref(),
+            &[ctx.accounts.minter.bump],
+        ];
+
+        for (item, metadata_info) in items.into_iter().zip(ctx.remaining_accounts.iter()) {
+            let metadata = Account::<token_metadata::MetadataAccount>::try_from(metadata_info)?;
+
+            let index_bytes = item.index.to_le_bytes();
+            let mint_seeds = &[MINT_KEY_V2, &index_bytes[..], &[item.mint_bump]];
+            let mint_addr = Pubkey::create_program_address(&mint_seeds[..], ctx.program_id)
+                .map_err(|_| MinterError::InvalidMintAddress)?;
+            if metadata.mint != mint_addr {
+                return Err(MinterError::InvalidMintAddress.into());
+            }
+
+            let data = token_metadata::state::DataV2 {
+                name: item.name,
+                uri: item.uri,
+                ..data_v2(&metadata)
+            };
+            validate_metadata(
+                &data.name,
+                &data.symbol,
+                &data.uri,
+                data.seller_fee_basis_points,
+            )?;
+            ctx.accounts
+                .minter
+                .check_mint_limits(&data.uri, data.seller_fee_basis_points)?;
+
+            let record = &mut ctx.accounts.reveal_record;
+            if item.index >= record.size {
+                return Err(MinterError::IndexOutOfRange.into());
+            }
+            if bitmap_is_set(&record.bitmap, item.index as usize) {
+                return Err(MinterError::AlreadyRevealed.into());
+            }
+            bitmap_set(&mut record.bitmap, item.index as usize);
+            record.revealed += 1;
+
+            token_metadata::update_metadata_accounts_v2(
+                ctx.accounts
+                    .into_update_metadata_context(metadata_info)
+                    .with_signer(&[&minter_seeds[..]]),
+                None,
+                Some(data),
+                None,
+                None,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Switches the minter to drawing metadata indices at random from a pool
+    /// of `size` indices instead of using the mint count. The draw can be
+    /// predicted by payers, see [`draw_index`].
+    pub fn initialize_index_pool(ctx: Context<InitializeIndexPool>, size: u32) -> Result<()> {
+        let pool = &mut ctx.accounts.index_pool;
+        pool.minter = ctx.accounts.minter.key();
+        pool.bump = *ctx.bumps.get("index_pool").unwrap();
+        pool.size = size;
+        pool.bitmap = vec![0; RevealRecord::bitmap_len(size)];
+        // mark the padding bits of the last byte as taken
+        for index in size as usize..pool.bitmap.len() * 8 {
+            bitmap_set(&mut pool.bitmap, index);
+        }
+
+        let minter = &mut ctx.accounts.minter;
+        minter.random_indices = true;
+        Ok(())
+    }
+
+    pub fn set_mint_limits(
+        ctx: Context<SetMintLimits>,
+        max_seller_fee_basis_points: Option<u16>,
+        uri_prefix: Option<String>,
+    ) -> Result<()> {
+        if matches!(max_seller_fee_basis_points, Some(max) if max > MAX_BASIS_POINTS) {
+            return Err(MinterError::InvalidSellerFeeBasisPoints.into());
+        }
+        if uri_prefix
+            .iter()
+            .any(|prefix| prefix.len() > token_metadata::state::MAX_URI_LENGTH)
+        {
+            return Err(MinterError::UriTooLong.into());
+        }
+
+        let minter = &mut ctx.accounts.minter;
+        minter.max_seller_fee_basis_points = max_seller_fee_basis_points;
+        minter.uri_prefix = uri_prefix;
+        Ok(())
+    }
+
+    /// Burns an NFT issued by the minter. Token, metadata and edition rent goes
+    /// back to the holder. NFTs in a sized collection also need the collection
+    /// metadata as a remaining account.
+    pub fn burn_nft<'info>(ctx: Context<'_, '_, '_, 'info, BurnNft<'info>>) -> Result<()> {
+        if !is_verified_creator(&ctx.accounts.metadata, ctx.accounts.minter.key) {
+            return Err(MinterError::NotIssuedByMinter.into());
+        }
+
+        token_metadata::burn_nft(
+            ctx.accounts
+                .into_burn_nft_context()
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+        )
+    }
+
+    /// Sets the lamports charged per mint and where they go. Unless
+    /// `skip_primary_sale` is set, paid mints flag the primary sale.
+    pub fn set_price(
+        ctx: Context<SetPrice>,
+        price: u64,
+        treasury: Pubkey,
+        skip_primary_sale: bool,
+    ) -> Result<()> {
+        let minter = &mut ctx.accounts.minter;
+        minter.price = price;
+        minter.treasury = treasury;
+        minter.skip_primary_sale = skip_primary_sale;
+        Ok(())
+    }
+
+    pub fn set_token_gate(ctx: Context<SetTokenGate>, gate: Option<TokenGate>) -> Result<()> {
+        let minter = &mut ctx.accounts.minter;
+        minter.gate = gate;
+        Ok(())
+    }
+
+    /// Requires every mint to burn an NFT of `collection`, if set.
+    pub fn set_burn_to_mint(ctx: Context<SetBurnToMint>, collection: Option<Pubkey>) -> Result<()> {
+        let minter = &mut ctx.accounts.minter;
+        minter.burn_to_mint = collection;
+        Ok(())
+    }
+
+    /// Adds a mint phase. While a minter has phases, mints go through the
+    /// phase active at the time with its price, gate and wallet limit.
+    /// Phases cannot overlap, the existing ones are passed as remaining
+    /// accounts in id order.
+    pub fn add_phase<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddPhase<'info>>,
+        id: u8,
+        config: PhaseConfig,
+    ) -> Result<()> {
+        config.validate(Clock::get()?.unix_timestamp)?;
+        check_phase_overlap(
+            &ctx.accounts.minter.key(),
+            &config,
+            None,
+            ctx.accounts.minter.phases,
+            ctx.remaining_accounts,
+        )?;
+
+        let phase = &mut ctx.accounts.phase;
+        phase.minter = ctx.accounts.minter.key();
+        phase.id = id;
+        phase.bump = *ctx.bumps.get("phase").unwrap();
+        phase.config = config;
+
+        let minter = &mut ctx.accounts.minter;
+        minter.phases = minter
+            .phases
+            .checked_add(1)
+            .ok_or(MinterError::InvalidPhaseConfig)?;
+        Ok(())
+    }
+
+    /// The other phases of the minter are passed as remaining accounts in id
+    /// order, as for [`add_phase`].
+    pub fn edit_phase<'info>(
+        ctx: Context<'_, '_, '_, 'info, EditPhase<'info>>,
+        config: PhaseConfig,
+    ) -> Result<()> {
+        config.validate(Clock::get()?.unix_timestamp)?;
+        check_phase_overlap(
+            &ctx.accounts.minter.key(),
+            &config,
+            Some(ctx.accounts.phase.id),
+            ctx.accounts.minter.phases - 1,
+            ctx.remaining_accounts,
+        )?;
+
+        let phase = &mut ctx.accounts.phase;
+        phase.config = config;
+        Ok(())
+    }
+
+    pub fn remove_phase(ctx: Context<RemovePhase>) -> Result<()> {
+        let minter = &mut ctx.accounts.minter;
+        minter.phases -= 1;
+        Ok(())
+    }
+
+    /// Splits mint proceeds across `payouts`, paid in `payment_mint` tokens
+    /// or in lamports. An empty list pays the treasury alone.
+    pub fn set_payouts(
+        ctx: Context<SetPayouts>,
+        payouts: Vec<Payout>,
+        payment_mint: Option<Pubkey>,
+    ) -> Result<()> {
+        if payouts.len() > MAX_PAYOUTS {
+            return Err(MinterError::TooManyPayouts.into());
+        }
+        let total: u32 = payouts.iter().map(|p| u32::from(p.basis_points)).sum();
+        if !payouts.is_empty() && total != u32::from(MAX_BASIS_POINTS) {
+            return Err(MinterError::InvalidPayouts.into());
+        }
+        if payment_mint.is_some() && ctx.accounts.minter.auction {
+            return Err(MinterError::SplPaymentUnsupported.into());
+        }
+        if payment_mint.is_some() && ctx.accounts.minter.escrow.is_some() {
+            return Err(MinterError::EscrowConflict.into());
+        }
+        if payment_mint.is_some() && ctx.accounts.minter.referral_basis_points.is_some() {
+            return Err(MinterError::ReferralConflict.into());
+        }
+
+        let minter = &mut ctx.accounts.minter;
+        minter.payouts = payouts;
+        minter.payment_mint = payment_mint;
+        Ok(())
+    }
+
+    /// Holds mint proceeds in the minter's vault. The owner can withdraw
+    /// them once `threshold` escrowed mints are sold, if that does not happen
+    /// by `deadline` holders can burn their NFT for a refund. The terms can
+    /// be changed with `update_escrow` and escrow turned off with
+    /// `close_escrow`.
+    pub fn set_escrow(ctx: Context<SetEscrow>, config: EscrowConfig) -> Result<()> {
+        if config.threshold == 0 || config.deadline <= Clock::get()?.unix_timestamp {
+            return Err(MinterError::InvalidEscrowConfig.into());
+        }
+        if ctx.accounts.minter.auction || ctx.accounts.minter.payment_mint.is_some() {
+            return Err(MinterError::EscrowConflict.into());
+        }
+        if ctx.accounts.minter.referral_basis_points.is_some() {
+            return Err(MinterError::ReferralConflict.into());
+        }
+
+        let vault = &mut ctx.accounts.vault;
+        vault.minter = ctx.accounts.minter.key();
+        vault.bump = *ctx.bumps.get("vault").unwrap();
+
+        let minter = &mut ctx.accounts.minter;
+        minter.escrow = Some(config);
+        Ok(())
+    }
+
+    /// Changes the escrow terms before any escrowed mint is sold.
+    pub fn update_escrow(ctx: Context<UpdateEscrow>, config: EscrowConfig) -> Result<()> {
+        if config.threshold == 0 || config.deadline <= Clock::get()?.unix_timestamp {
+            return Err(MinterError::InvalidEscrowConfig.into());
+        }
+        if ctx.accounts.vault.sold > 0 {
+            return Err(MinterError::EscrowInUse.into());
+        }
+
+        let minter = &mut ctx.accounts.minter;
+        minter.escrow = Some(config);
+        Ok(())
+    }
+
+    /// Turns escrow off and closes the vault, either before any escrowed mint
+    /// is sold or once a successful drop has been withdrawn.
+    pub fn close_escrow(ctx: Context<CloseEscrow>) -> Result<()> {
+        let escrow = ctx
+            .accounts
+            .minter
+            .escrow
+            .as_ref()
+            .ok_or(MinterError::InvalidVault)?;
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let withdrawn =
+            vault_info.lamports() <= Rent::get()?.minimum_balance(vault_info.data_len());
+        let sold = ctx.accounts.vault.sold;
+        if sold > 0 && (sold < escrow.threshold || !withdrawn) {
+            return Err(MinterError::EscrowInUse.into());
+        }
+
+        let minter = &mut ctx.accounts.minter;
+        minter.escrow = None;
+        Ok(())
+    }
+
+    /// Splits the escrowed proceeds across the payouts once the drop
+    /// succeeded. The payout recipients, or the treasury if there are none,
+    /// are passed as remaining accounts (mut), in payout order.
+    pub fn withdraw_escrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawEscrow<'info>>,
+    ) -> Result<()> {
+        let escrow = ctx
+            .accounts
+            .minter
+            .escrow
+            .as_ref()
+            .ok_or(MinterError::InvalidVault)?;
+        if ctx.accounts.vault.sold < escrow.threshold {
+            return Err(MinterError::EscrowThresholdNotMet.into());
+        }
+
+        let vault_info = ctx.accounts.vault.to_account_info();
+        let amount = vault_info
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(vault_info.data_len()));
+
+        let payouts = ctx.accounts.minter.payout_recipients();
+        if ctx.remaining_accounts.len() != payouts.len() {
+            return Err(MinterError::InvalidPayoutAccount.into());
+        }
+        let shares = split_proceeds(amount, &payouts);
+        for ((payout, share), recipient) in payouts.iter().zip(shares).zip(ctx.remaining_accounts) {
+            if recipient.key != &payout.recipient {
+                return Err(MinterError::InvalidPayoutAccount.into());
+            }
+            move_lamports(&vault_info, recipient, share)?;
+        }
+        Ok(())
+    }
+
+    /// Burns an NFT of a failed drop and refunds the holder what was paid for
+    /// it. NFTs in a sized collection also need the collection metadata as a
+    /// remaining account.
+    pub fn claim_refund<'info>(ctx: Context<'_, '_, '_, 'info, ClaimRefund<'info>>) -> Result<()> {
+        let escrow = ctx
+            .accounts
+            .minter
+            .escrow
+            .as_ref()
+            .ok_or(MinterError::InvalidVault)?;
+        if Clock::get()?.unix_timestamp < escrow.deadline
+            || ctx.accounts.vault.sold >= escrow.threshold
+            || ctx.accounts.receipt.deadline != escrow.deadline
+        {
+            return Err(MinterError::RefundUnavailable.into());
+        }
+
+        token_metadata::burn_nft(
+            ctx.accounts
+                .into_burn_nft_context()
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+        )?;
+
+        move_lamports(
+            &ctx.accounts.vault.to_account_info(),
+            &ctx.accounts.holder.to_account_info(),
+            ctx.accounts.receipt.amount,
+        )
+    }
+
+    /// Switches the minter to Token-2022 mints with the given extensions. Its
+    /// mints then only go through `mint_token_2022`.
+    pub fn set_token_2022(
+        ctx: Context<SetToken2022>,
+        config: Option<Token2022Config>,
+    ) -> Result<()> {
+        let minter = &mut ctx.accounts.minter;
+        minter.token_2022 = config;
+        Ok(())
+    }
+
+    /// Allows `mint_unordered`.
+    pub fn set_unordered(ctx: Context<SetUnordered>, enabled: bool) -> Result<()> {
+        let minter = &mut ctx.accounts.minter;
+        minter.unordered = enabled;
+        Ok(())
+    }
+
+    /// Lets mints name a referrer that gets `basis_points` of the price paid
+    /// to the treasury. `None` turns referrals off. Referrals are paid in
+    /// lamports at mint time, so they cannot be combined with a payment mint
+    /// or with escrow.
+    pub fn set_referral(ctx: Context<SetReferral>, basis_points: Option<u16>) -> Result<()> {
+        if matches!(basis_points, Some(bps) if bps > MAX_BASIS_POINTS) {
+            return Err(MinterError::InvalidReferralBasisPoints.into());
+        }
+        let minter = &ctx.accounts.minter;
+        if basis_points.is_some() && (minter.payment_mint.is_some() || minter.escrow.is_some()) {
+            return Err(MinterError::ReferralConflict.into());
+        }
+        let minter = &mut ctx.accounts.minter;
+        minter.referral_basis_points = basis_points;
+        Ok(())
+    }
+
+    /// Prices mints by a Dutch auction until it is settled.
+    pub fn start_auction(ctx: Context<StartAuction>, config: DutchAuction) -> Result<()> {
+        config.validate()?;
+        if ctx.accounts.minter.payment_mint.is_some() {
+            return Err(MinterError::SplPaymentUnsupported.into());
+        }
+        if ctx.accounts.minter.escrow.is_some() {
+            return Err(MinterError::EscrowConflict.into());
+        }
+
+        let auction = &mut ctx.accounts.auction;
+        auction.minter = ctx.accounts.minter.key();
+        auction.bump = *ctx.bumps.get("auction").unwrap();
+        auction.config = config;
+
+        let minter = &mut ctx.accounts.minter;
+        minter.auction = true;
+        Ok(())
+    }
+
+    /// Emits the current auction price, meant to be simulated by clients.
+    pub fn report_auction_price(ctx: Context<ReportAuctionPrice>) -> Result<()> {
+        let timestamp = Clock::get()?.unix_timestamp;
+        emit!(AuctionPriceEvent {
+            minter: ctx.accounts.auction.minter,
+            price: ctx.accounts.auction.config.price_at(timestamp),
+            timestamp,
+        });
+        Ok(())
+    }
+
+    /// Ends the auction at the last price paid and returns the minter to
+    /// fixed pricing.
+    pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
+        let auction = &mut ctx.accounts.auction;
+        let clearing_price = if auction.sold > 0 {
+            auction.last_price
+        } else {
+            auction.config.floor_price
+        };
+        auction.clearing_price = Some(clearing_price);
+
+        let minter = &mut ctx.accounts.minter;
+        minter.auction = false;
         Ok(())
     }
 
-    pub fn binding_collection(ctx: Context<BindingCollection>) -> Result<()> {
-        let minter_seeds = &[MINTER_KEY_V2, ctx.accounts.minter.owner.as_ref()];
-        let (_pda, bump_seed) = Pubkey::find_program_address(&minter_seeds[..], ctx.program_id);
+    /// Refunds a buyer what they paid above the clearing price.
+    pub fn claim_auction_rebate(ctx: Context<ClaimAuctionRebate>) -> Result<()> {
+        let clearing_price = ctx
+            .accounts
+            .auction
+            .clearing_price
+            .ok_or(MinterError::AuctionNotSettled)?;
+        let receipt = &ctx.accounts.receipt;
+        let rebate = receipt
+            .count
+            .checked_mul(clearing_price)
+            .and_then(|cost| receipt.total_paid.checked_sub(cost))
+            .ok_or(MinterError::PaymentOverflow)?;
+
+        move_lamports(
+            &ctx.accounts.auction.to_account_info(),
+            &ctx.accounts.buyer.to_account_info(),
+            rebate,
+        )
+    }
+
+    /// Sends the proceeds held for rebates, at the clearing price, to the
+    /// treasury.
+    pub fn withdraw_auction_proceeds(ctx: Context<WithdrawAuctionProceeds>) -> Result<()> {
+        let auction = &mut ctx.accounts.auction;
+        let clearing_price = auction
+            .clearing_price
+            .ok_or(MinterError::AuctionNotSettled)?;
+        let amount = auction
+            .sold
+            .checked_mul(clearing_price)
+            .and_then(|proceeds| proceeds.checked_sub(auction.withdrawn))
+            .ok_or(MinterError::PaymentOverflow)?;
+        auction.withdrawn += amount;
+
+        move_lamports(
+            &auction.to_account_info(),
+            &ctx.accounts.treasury.to_account_info(),
+            amount,
+        )
+    }
+
+    /// Binds the collection to the minter. With `size`, an unsized collection
+    /// is turned into a sized one starting at that many items.
+    pub fn binding_collection(ctx: Context<BindingCollection>, size: Option<u64>) -> Result<()> {
         let minter_seeds = &[
             MINTER_KEY_V2,
             ctx.accounts.minter.owner.as_ref(),
-            &[bump_seed],
+            &[ctx.accounts.minter.bump],
         ];
 
         token_metadata::approve_collection_authority(
@@ -68,131 +833,707 @@ pub mod minter {
                 .with_signer(&[&minter_seeds[..]]),
         )?;
 
+        if let Some(size) = size {
+            token_metadata::set_collection_size(
+                ctx.accounts.into_set_collection_size_context(),
+                size,
+            )?;
+        }
+
+        let registry = &mut ctx.accounts.collection_registry;
+        registry.minter = ctx.accounts.minter.key();
+        registry.collection = ctx.accounts.mint.key();
+        registry.bump = *ctx.bumps.get("collection_registry").unwrap();
+
         let minter = &mut ctx.accounts.minter;
         minter.collection = Some(ctx.accounts.mint.key());
 
         Ok(())
     }
 
-    pub fn mint_for(
-        ctx: Context<MintFor>,
+    /// Creates a collection NFT held by the owner and binds it to the minter.
+    /// Its update authority is the minter if `minter_update_authority`, the
+    /// owner otherwise. `id` tells apart the collections of the minter.
+    pub fn create_collection(
+        ctx: Context<CreateCollection>,
+        id: u32,
+        mint_bump: u8,
         name: String,
         symbol: String,
         uri: String,
-        seller_fee_basis_points: u16,
-        is_mutable: bool,
+        size: Option<u64>,
+        minter_update_authority: bool,
     ) -> Result<()> {
-        let minter = &ctx.accounts.minter;
-        let minter_count_bytes = minter.count.to_le_bytes();
+        validate_metadata(&name, &symbol, &uri, 0)?;
 
-        let minter_seeds = &[MINTER_KEY, ctx.accounts.minter.owner.as_ref()];
-        let (_pda, bump_seed) = Pubkey::find_program_address(&minter_seeds[..], ctx.program_id);
-        let minter_seeds = &[MINTER_KEY, ctx.accounts.minter.owner.as_ref(), &[bump_seed]];
+        let minter_key = ctx.accounts.minter.key();
+        let minter_seeds = &[
+            MINTER_KEY_V2,
+            ctx.accounts.minter.owner.as_ref(),
+            &[ctx.accounts.minter.bump],
+        ];
 
         // check that mint address is a valid program derived address
-        let mint_seeds = &[MINT_KEY, &minter_count_bytes[..]];
-        let (mint_addr, mint_seed) = Pubkey::find_program_address(&mint_seeds[..], ctx.program_id);
+        let id_bytes = id.to_le_bytes();
+        let mint_seeds = &[
+            COLLECTION_MINT_KEY,
+            minter_key.as_ref(),
+            &id_bytes[..],
+            &[mint_bump],
+        ];
+        let mint_addr = Pubkey::create_program_address(&mint_seeds[..], ctx.program_id)
+            .map_err(|_| MinterError::InvalidMintAddress)?;
         if ctx.accounts.mint.key != &mint_addr {
             return Err(MinterError::InvalidMintAddress.into());
         }
-        let mint_seeds = &[MINT_KEY, &minter_count_bytes[..], &[mint_seed]];
-        allocate_mint_for(&ctx, &[&mint_seeds[..]])?;
+        create_or_allocate_account_raw(
+            *ctx.accounts.token_program.key,
+            &ctx.accounts.mint.to_account_info(),
+            &ctx.accounts.rent.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.authority.to_account_info(),
+            Mint::LEN,
+            &[&mint_seeds[..]],
+        )?;
 
         token::initialize_mint(
             ctx.accounts.into_initialize_mint_context(),
             0,
-            &ctx.accounts.minter.key(),
+            &minter_key,
             None,
         )?;
 
-        associated_token::create(
-            ctx.accounts
-                .into_create_associated_token_context(&ctx.accounts.payer),
-        )?;
+        associated_token::create(ctx.accounts.into_create_associated_token_context())?;
 
         token::mint_to(
             ctx.accounts
-                .into_token_mint_to_context(&ctx.accounts.minter.to_account_info())
+                .into_token_mint_to_context()
                 .with_signer(&[&minter_seeds[..]]),
             1,
         )?;
 
+        let update_authority = if minter_update_authority {
+            ctx.accounts.minter.to_account_info()
+        } else {
+            ctx.accounts.authority.to_account_info()
+        };
+
         token_metadata::create_metadata_account_v2(
             ctx.accounts
-                .into_create_metadata_context(
-                    &ctx.accounts.payer,
-                    &ctx.accounts.minter.to_account_info(),
-                )
+                .into_create_metadata_context(&update_authority)
                 .with_signer(&[&minter_seeds[..]]),
             name,
             symbol,
             uri,
-            Some(vec![
-                token_metadata::state::Creator {
-                    address: *ctx.accounts.payer.key,
-                    verified: false,
-                    share: 98,
-                },
-                token_metadata::state::Creator {
-                    address: ctx.accounts.minter.key(),
-                    verified: true,
-                    share: 2,
-                },
-            ]),
-            seller_fee_basis_points,
+            Some(vec![token_metadata::state::Creator {
+                address: minter_key,
+                verified: true,
+                share: 100,
+            }]),
+            0,
+            true,
             true,
-            is_mutable,
             None,
             None,
         )?;
 
         token_metadata::create_master_edition_v3(
             ctx.accounts
-                .into_create_master_edition_context(
-                    &ctx.accounts.payer,
-                    &ctx.accounts.minter.to_account_info(),
-                )
+                .into_create_master_edition_context(&update_authority)
                 .with_signer(&[&minter_seeds[..]]),
             Some(0),
         )?;
 
-        token_metadata::sign_metadata(
-            ctx.accounts.into_sign_metadata_context(&ctx.accounts.payer),
+        token_metadata::approve_collection_authority(
+            ctx.accounts
+                .into_approve_collection_authority_context(&update_authority)
+                .with_signer(&[&minter_seeds[..]]),
         )?;
 
-        ctx.accounts.minter.reload()?;
+        if let Some(size) = size {
+            token_metadata::set_collection_size(
+                ctx.accounts
+                    .into_set_collection_size_context(&update_authority)
+                    .with_signer(&[&minter_seeds[..]]),
+                size,
+            )?;
+        }
+
+        let registry = &mut ctx.accounts.collection_registry;
+        registry.minter = minter_key;
+        registry.collection = mint_addr;
+        registry.bump = *ctx.bumps.get("collection_registry").unwrap();
+
         let minter = &mut ctx.accounts.minter;
-        minter.count += 1;
+        minter.collection = Some(mint_addr);
+
+        Ok(())
+    }
 
+    /// Adds the collection bound before the registry existed to it.
+    pub fn register_collection(ctx: Context<RegisterCollection>) -> Result<()> {
+        let registry = &mut ctx.accounts.collection_registry;
+        registry.minter = ctx.accounts.minter.key();
+        registry.collection = ctx.accounts.collection_mint.key();
+        registry.bump = *ctx.bumps.get("collection_registry").unwrap();
         Ok(())
     }
 
-    pub fn mint_with_collection(
-        ctx: Context<MintWithCollection>,
+    /// Creates a fungible asset, or a semi-fungible one with zero `decimals`:
+    /// a mint with metadata and no master edition whose mint authority stays
+    /// with the minter. `initial_supply` goes to the owner.
+    pub fn create_fungible(
+        ctx: Context<CreateFungible>,
+        id: u32,
+        mint_bump: u8,
+        decimals: u8,
+        initial_supply: u64,
+        max_supply: Option<u64>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        validate_metadata(&name, &symbol, &uri, 0)?;
+        if matches!(max_supply, Some(max) if initial_supply > max) {
+            return Err(MinterError::InvalidFungibleSupply.into());
+        }
+
+        let minter_key = ctx.accounts.minter.key();
+        let minter_seeds = &[
+            MINTER_KEY_V2,
+            ctx.accounts.minter.owner.as_ref(),
+            &[ctx.accounts.minter.bump],
+        ];
+
+        // check that mint address is a valid program derived address
+        let id_bytes = id.to_le_bytes();
+        let mint_seeds = &[
+            FUNGIBLE_MINT_KEY,
+            minter_key.as_ref(),
+            &id_bytes[..],
+            &[mint_bump],
+        ];
+        let mint_addr = Pubkey::create_program_address(&mint_seeds[..], ctx.program_id)
+            .map_err(|_| MinterError::InvalidMintAddress)?;
+        if ctx.accounts.mint.key != &mint_addr {
+            return Err(MinterError::InvalidMintAddress.into());
+        }
+        create_or_allocate_account_raw(
+            *ctx.accounts.token_program.key,
+            &ctx.accounts.mint.to_account_info(),
+            &ctx.accounts.rent.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.authority.to_account_info(),
+            Mint::LEN,
+            &[&mint_seeds[..]],
+        )?;
+
+        token::initialize_mint(
+            ctx.accounts.into_initialize_mint_context(),
+            decimals,
+            &minter_key,
+            None,
+        )?;
+
+        token_metadata::create_metadata_account_v2(
+            ctx.accounts
+                .into_create_metadata_context()
+                .with_signer(&[&minter_seeds[..]]),
+            name,
+            symbol,
+            uri,
+            Some(vec![token_metadata::state::Creator {
+                address: minter_key,
+                verified: true,
+                share: 100,
+            }]),
+            0,
+            true,
+            true,
+            None,
+            None,
+        )?;
+
+        if initial_supply > 0 {
+            associated_token::create(ctx.accounts.into_create_associated_token_context())?;
+            token::mint_to(
+                ctx.accounts
+                    .into_token_mint_to_context()
+                    .with_signer(&[&minter_seeds[..]]),
+                initial_supply,
+            )?;
+        }
+
+        let asset = &mut ctx.accounts.asset;
+        asset.minter = minter_key;
+        asset.mint = mint_addr;
+        asset.id = id;
+        asset.max_supply = max_supply;
+        asset.bump = *ctx.bumps.get("asset").unwrap();
+        Ok(())
+    }
+
+    /// Mints more of a fungible asset to `destination`.
+    pub fn mint_fungible(ctx: Context<MintFungible>, amount: u64) -> Result<()> {
+        let supply = ctx
+            .accounts
+            .mint
+            .supply
+            .checked_add(amount)
+            .ok_or(MinterError::SupplyCapExceeded)?;
+        if matches!(ctx.accounts.asset.max_supply, Some(max) if supply > max) {
+            return Err(MinterError::SupplyCapExceeded.into());
+        }
+
+        let minter_seeds = &[
+            MINTER_KEY_V2,
+            ctx.accounts.minter.owner.as_ref(),
+            &[ctx.accounts.minter.bump],
+        ];
+        token::mint_to(
+            ctx.accounts
+                .into_token_mint_to_context()
+                .with_signer(&[&minter_seeds[..]]),
+            amount,
+        )
+    }
+
+    /// Removes an NFT from the collection, signed by the minter through its
+    /// collection authority record.
+    pub fn unverify_collection_item(ctx: Context<CollectionItem>) -> Result<()> {
+        let minter_seeds = &[
+            MINTER_KEY_V2,
+            ctx.accounts.minter.owner.as_ref(),
+            &[ctx.accounts.minter.bump],
+        ];
+        let collection_authority_record =
+            ctx.accounts.collection_authority_record.to_account_info();
+
+        if ctx.accounts.collection.collection_details.is_some() {
+            token_metadata::unverify_sized_collection_item(
+                ctx.accounts
+                    .into_unverify_sized_collection_item_context()
+                    .with_remaining_accounts(vec![collection_authority_record])
+                    .with_signer(&[&minter_seeds[..]]),
+            )
+        } else {
+            token_metadata::unverify_collection(
+                ctx.accounts
+                    .into_unverify_collection_context()
+                    .with_remaining_accounts(vec![collection_authority_record])
+                    .with_signer(&[&minter_seeds[..]]),
+            )
+        }
+    }
+
+    /// Sets the collection of an NFT issued by the minter and verifies it,
+    /// signed by the minter through its collection authority record.
+    pub fn set_and_verify_collection_item(ctx: Context<CollectionItem>) -> Result<()> {
+        let minter_seeds = &[
+            MINTER_KEY_V2,
+            ctx.accounts.minter.owner.as_ref(),
+            &[ctx.accounts.minter.bump],
+        ];
+        let collection_authority_record =
+            ctx.accounts.collection_authority_record.to_account_info();
+        let sized = ctx.accounts.collection.collection_details.is_some();
+
+        // Token Metadata only sets and verifies in one go when the NFT and the
+        // collection share an update authority
+        if ctx.accounts.metadata.update_authority == ctx.accounts.collection.update_authority {
+            if sized {
+                token_metadata::set_and_verify_sized_collection_item(
+                    ctx.accounts
+                        .into_set_and_verify_sized_collection_item_context()
+                        .with_remaining_accounts(vec![collection_authority_record])
+                        .with_signer(&[&minter_seeds[..]]),
+                )?;
+            } else {
+                token_metadata::set_and_verify_collection(
+                    ctx.accounts
+                        .into_set_and_verify_collection_context()
+                        .with_remaining_accounts(vec![collection_authority_record])
+                        .with_signer(&[&minter_seeds[..]]),
+                )?;
+            }
+            return Ok(());
+        }
+
+        let collection_mint = ctx.accounts.collection_mint.key();
+        if ctx.accounts.metadata.collection.as_ref().map(|c| c.key) != Some(collection_mint) {
+            token_metadata::update_metadata_accounts_v2(
+                ctx.accounts
+                    .into_update_metadata_context()
+                    .with_signer(&[&minter_seeds[..]]),
+                None,
+                Some(token_metadata::state::DataV2 {
+                    collection: Some(token_metadata::state::Collection {
+                        key: collection_mint,
+                        verified: false,
+                    }),
+                    ..data_v2(&ctx.accounts.metadata)
+                }),
+                None,
+                None,
+            )?;
+        }
+
+        verify_collection_item(
+            sized,
+            ctx.accounts
+                .into_verify_collection_context()
+                .with_remaining_accounts(vec![collection_authority_record])
+                .with_signer(&[&minter_seeds[..]]),
+        )
+    }
+
+    /// Adds the v1 NFTs at `start..start + mint_bumps.len()` to the collection
+    /// bound to the v2 minter of the same owner. Their metadata accounts are
+    /// passed as remaining accounts, in index order. Items already verified in
+    /// the collection are skipped.
+    pub fn add_v1_items_to_collection<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddV1ItemsToCollection<'info>>,
+        start: u32,
+        mint_bumps: Vec<u8>,
+    ) -> Result<()> {
+        if mint_bumps.len() != ctx.remaining_accounts.len() {
+            return Err(MinterError::MismatchedItemAccounts.into());
+        }
+
+        let minter_seeds = &[
+            MINTER_KEY,
+            ctx.accounts.minter.owner.as_ref(),
+            &[ctx.accounts.minter.bump],
+        ];
+        let minter_v2_seeds = &[
+            MINTER_KEY_V2,
+            ctx.accounts.minter_v2.owner.as_ref(),
+            &[ctx.accounts.minter_v2.bump],
+        ];
+        let collection_authority_record =
+            ctx.accounts.collection_authority_record.to_account_info();
+        let collection_mint = ctx.accounts.collection_mint.key();
+        let sized = ctx.accounts.collection.collection_details.is_some();
+        // Token Metadata only sets and verifies in one go when the NFT and the
+        // collection share an update authority
+        let shared_authority =
+            ctx.accounts.collection.update_authority == ctx.accounts.minter.key();
+
+        for (offset, (mint_bump, metadata_info)) in mint_bumps
+            .iter()
+            .zip(ctx.remaining_accounts.iter())
+            .enumerate()
+        {
+            let index = start
+                .checked_add(offset as u32)
+                .ok_or(MinterError::IndexOutOfRange)?;
+            if u64::from(index) >= ctx.accounts.minter.count {
+                return Err(MinterError::IndexOutOfRange.into());
+            }
+
+            let metadata = Account::<token_metadata::MetadataAccount>::try_from(metadata_info)?;
+            let index_bytes = index.to_le_bytes();
+            let mint_seeds = &[MINT_KEY, &index_bytes[..], &[*mint_bump]];
+            let mint_addr = Pubkey::create_program_address(&mint_seeds[..], ctx.program_id)
+                .map_err(|_| MinterError::InvalidMintAddress)?;
+            if metadata.mint != mint_addr {
+                return Err(MinterError::InvalidMintAddress.into());
+            }
+
+            match &metadata.collection {
+                Some(collection) if collection.key == collection_mint && collection.verified => {
+                    continue
+                }
+                _ => {}
+            }
+
+            if shared_authority {
+                if sized {
+                    token_metadata::set_and_verify_sized_collection_item(
+                        ctx.accounts
+                            .into_set_and_verify_sized_collection_item_context(metadata_info)
+                            .with_remaining_accounts(vec![collection_authority_record.clone()])
+                            .with_signer(&[&minter_v2_seeds[..]]),
+                    )?;
+                } else {
+                    token_metadata::set_and_verify_collection(
+                        ctx.accounts
+                            .into_set_and_verify_collection_context(metadata_info)
+                            .with_remaining_accounts(vec![collection_authority_record.clone()])
+                            .with_signer(&[&minter_v2_seeds[..]]),
+                    )?;
+                }
+                continue;
+            }
+
+            if metadata.collection.as_ref().map(|c| c.key) != Some(collection_mint) {
+                token_metadata::update_metadata_accounts_v2(
+                    ctx.accounts
+                        .into_update_metadata_context(metadata_info)
+                        .with_signer(&[&minter_seeds[..]]),
+                    None,
+                    Some(token_metadata::state::DataV2 {
+                        collection: Some(token_metadata::state::Collection {
+                            key: collection_mint,
+                            verified: false,
+                        }),
+                        ..data_v2(&metadata)
+                    }),
+                    None,
+                    None,
+                )?;
+            }
+
+            verify_collection_item(
+                sized,
+                ctx.accounts
+                    .into_verify_collection_context(metadata_info)
+                    .with_remaining_accounts(vec![collection_authority_record.clone()])
+                    .with_signer(&[&minter_v2_seeds[..]]),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn mint_for(
+        ctx: Context<MintFor>,
+        mint_bump: u8,
+        record_bump: u8,
+        name: String,
+        symbol: String,
+        uri: String,
+        seller_fee_basis_points: u16,
+        is_mutable: bool,
+    ) -> Result<()> {
+        validate_metadata(&name, &symbol, &uri, seller_fee_basis_points)?;
+
+        let minter = &ctx.accounts.minter;
+        let minter_count_seed = mint_index_seed(minter.count);
+
+        let minter_seeds = &[
+            MINTER_KEY,
+            ctx.accounts.minter.owner.as_ref(),
+            &[ctx.accounts.minter.bump],
+        ];
+
+        // check that mint address is a valid program derived address
+        let mint_seeds = &[MINT_KEY, &minter_count_seed[..], &[mint_bump]];
+        let mint_addr = Pubkey::create_program_address(&mint_seeds[..], ctx.program_id)
+            .map_err(|_| MinterError::InvalidMintAddress)?;
+        if ctx.accounts.mint.key != &mint_addr {
+            return Err(MinterError::InvalidMintAddress.into());
+        }
+        allocate_mint_for(&ctx, &[&mint_seeds[..]])?;
+
+        token::initialize_mint(
+            ctx.accounts.into_initialize_mint_context(),
+            0,
+            &ctx.accounts.minter.key(),
+            None,
+        )?;
+
+        associated_token::create(
+            ctx.accounts
+                .into_create_associated_token_context(&ctx.accounts.payer),
+        )?;
+
+        token::mint_to(
+            ctx.accounts
+                .into_token_mint_to_context(&ctx.accounts.minter.to_account_info())
+                .with_signer(&[&minter_seeds[..]]),
+            1,
+        )?;
+
+        token_metadata::create_metadata_account_v2(
+            ctx.accounts
+                .into_create_metadata_context(
+                    &ctx.accounts.payer,
+                    &ctx.accounts.minter.to_account_info(),
+                )
+                .with_signer(&[&minter_seeds[..]]),
+            name,
+            symbol,
+            uri,
+            Some(vec![
+                token_metadata::state::Creator {
+                    address: *ctx.accounts.payer.key,
+                    verified: false,
+                    share: 98,
+                },
+                token_metadata::state::Creator {
+                    address: ctx.accounts.minter.key(),
+                    verified: true,
+                    share: 2,
+                },
+            ]),
+            seller_fee_basis_points,
+            true,
+            is_mutable,
+            None,
+            None,
+        )?;
+
+        token_metadata::create_master_edition_v3(
+            ctx.accounts
+                .into_create_master_edition_context(
+                    &ctx.accounts.payer,
+                    &ctx.accounts.minter.to_account_info(),
+                )
+                .with_signer(&[&minter_seeds[..]]),
+            Some(0),
+        )?;
+
+        token_metadata::sign_metadata(
+            ctx.accounts.into_sign_metadata_context(&ctx.accounts.payer),
+        )?;
+
+        let clock = Clock::get()?;
+        create_mint_record(
+            &ctx.accounts.mint_record.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.rent.to_account_info(),
+            MintRecord {
+                mint: ctx.accounts.mint.key(),
+                minter: ctx.accounts.minter.key(),
+                index: ctx.accounts.minter.count,
+                metadata_index: ctx.accounts.minter.count,
+                shard: None,
+                recipient: ctx.accounts.recipient.key(),
+                slot: clock.slot,
+                timestamp: clock.unix_timestamp,
+                bump: record_bump,
+            },
+        )?;
+
+        ctx.accounts.minter.reload()?;
+        let minter = &mut ctx.accounts.minter;
+        minter.count = minter
+            .count
+            .checked_add(1)
+            .ok_or(MinterError::CounterOverflow)?;
+
+        Ok(())
+    }
+
+    /// Extra accounts, in this order, depending on the minter config:
+    /// - `phases > 0`: see [`enter_phase`]
+    /// - `random_indices`: index pool (mut), slot hashes sysvar
+    /// - `auction`: the ['auction', minter] account (mut), then in rebate mode
+    ///   the payer's ['auction_receipt', auction, payer] (mut)
+    /// - otherwise `escrow`: see [`escrow_payment`]
+    /// - a price above zero that is not escrowed: see [`distribute_proceeds`]
+    /// - the gate of the phase, or of the minter: see [`check_token_gate`]
+    /// - `burn_to_mint`: see [`burn_for_mint`]
+    /// - `referral_basis_points`, optionally: the referrer (mut) and its
+    ///   ['referrer_stats', minter, referrer] (mut)
+    pub fn mint_with_collection<'info>(
+        ctx: Context<'_, '_, '_, 'info, MintWithCollection<'info>>,
+        mint_bump: u8,
+        record_bump: u8,
         name: String,
         symbol: String,
         uri: String,
         seller_fee_basis_points: u16,
         is_mutable: bool,
+        collection: Pubkey,
     ) -> Result<()> {
+        if ctx.accounts.collection_mint.key() != collection {
+            return Err(MinterError::MismatchedCollection.into());
+        }
+
+        let remaining_accounts = &mut ctx.remaining_accounts.iter();
+
         let minter = &ctx.accounts.minter;
-        let minter_count_bytes = minter.count.to_le_bytes();
+        let phase = if minter.phases > 0 {
+            Some(enter_phase(
+                &minter.key(),
+                &ctx.accounts.payer.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                &ctx.accounts.rent.to_account_info(),
+                remaining_accounts,
+            )?)
+        } else {
+            None
+        };
+        let price = phase.as_ref().map_or(minter.price, |phase| phase.price);
+        let gate = match &phase {
+            Some(phase) => &phase.gate,
+            None => &minter.gate,
+        };
+
+        let minter_count_seed = mint_index_seed(minter.count);
+        let index = if minter.random_indices {
+            draw_index(
+                ctx.program_id,
+                &minter.key(),
+                ctx.accounts.payer.key,
+                next_account_info(remaining_accounts)?,
+                next_account_info(remaining_accounts)?,
+            )?
+            .into()
+        } else {
+            minter.count
+        };
+        let (name, symbol, uri) = minter.resolve_metadata(index, name, symbol, uri)?;
+        validate_metadata(&name, &symbol, &uri, seller_fee_basis_points)?;
+        minter.check_mint_limits(&uri, seller_fee_basis_points)?;
+        if minter.hidden.is_some() && !is_mutable {
+            return Err(MinterError::HiddenMintMustBeMutable.into());
+        }
+
+        let (price_paid, escrowed) = if minter.auction {
+            charge_auction(
+                minter,
+                &ctx.accounts.payer.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                &ctx.accounts.rent.to_account_info(),
+                remaining_accounts,
+            )?
+        } else if let Some(escrow) = &minter.escrow {
+            escrow_payment(
+                escrow,
+                &minter.key(),
+                &ctx.accounts.mint.key(),
+                price,
+                &ctx.accounts.payer.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                &ctx.accounts.rent.to_account_info(),
+                remaining_accounts,
+            )?;
+            (price, true)
+        } else {
+            (price, false)
+        };
+        settle_mint(
+            &minter.key(),
+            minter,
+            &ctx.accounts.mint.key(),
+            gate.as_ref(),
+            price_paid,
+            escrowed,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.rent.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.token_metadata_program.to_account_info(),
+            remaining_accounts,
+        )?;
 
-        let minter_seeds = &[MINTER_KEY_V2, ctx.accounts.minter.owner.as_ref()];
-        let (_pda, bump_seed) = Pubkey::find_program_address(&minter_seeds[..], ctx.program_id);
         let minter_seeds = &[
             MINTER_KEY_V2,
             ctx.accounts.minter.owner.as_ref(),
-            &[bump_seed],
+            &[ctx.accounts.minter.bump],
         ];
 
         // check that mint address is a valid program derived address
-        let mint_seeds = &[MINT_KEY_V2, &minter_count_bytes[..]];
-        let (mint_addr, mint_seed) = Pubkey::find_program_address(&mint_seeds[..], ctx.program_id);
+        let mint_seeds = &[MINT_KEY_V2, &minter_count_seed[..], &[mint_bump]];
+        let mint_addr = Pubkey::create_program_address(&mint_seeds[..], ctx.program_id)
+            .map_err(|_| MinterError::InvalidMintAddress)?;
         if ctx.accounts.mint.key != &mint_addr {
             return Err(MinterError::InvalidMintAddress.into());
         }
-        let mint_seeds = &[MINT_KEY_V2, &minter_count_bytes[..], &[mint_seed]];
         allocate_mint_with_collection(&ctx, &[&mint_seeds[..]])?;
 
         token::initialize_mint(
@@ -239,12 +1580,10 @@ pub mod minter {
             seller_fee_basis_points,
             true,
             is_mutable,
-            minter
-                .collection
-                .map(|mint| token_metadata::state::Collection {
-                    key: mint,
-                    verified: false,
-                }),
+            Some(token_metadata::state::Collection {
+                key: collection,
+                verified: false,
+            }),
             None,
         )?;
 
@@ -258,120 +1597,2369 @@ pub mod minter {
             Some(0),
         )?;
 
-        token_metadata::sign_metadata(
-            ctx.accounts.into_sign_metadata_context(&ctx.accounts.payer),
-        )?;
+        token_metadata::sign_metadata(
+            ctx.accounts.into_sign_metadata_context(&ctx.accounts.payer),
+        )?;
+
+        verify_collection_item(
+            ctx.accounts.collection.collection_details.is_some(),
+            ctx.accounts
+                .into_verify_collection_context()
+                .with_remaining_accounts(vec![ctx
+                    .accounts
+                    .collection_authority_record
+                    .to_account_info()
+                    .clone()])
+                .with_signer(&[&minter_seeds[..]]),
+        )?;
+        flag_primary_sale(
+            &ctx.accounts.minter,
+            price_paid,
+            ctx.accounts.into_update_primary_sale_happened_context(),
+        )?;
+
+        let clock = Clock::get()?;
+        create_mint_record(
+            &ctx.accounts.mint_record.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.rent.to_account_info(),
+            MintRecord {
+                mint: ctx.accounts.mint.key(),
+                minter: ctx.accounts.minter.key(),
+                index: ctx.accounts.minter.count,
+                metadata_index: index,
+                shard: None,
+                recipient: ctx.accounts.recipient.key(),
+                slot: clock.slot,
+                timestamp: clock.unix_timestamp,
+                bump: record_bump,
+            },
+        )?;
+
+        ctx.accounts.minter.reload()?;
+        let minter = &mut ctx.accounts.minter;
+        minter.count = minter
+            .count
+            .checked_add(1)
+            .ok_or(MinterError::CounterOverflow)?;
+        Ok(())
+    }
+
+    /// Mints into an unsized collection without writing the minter, so
+    /// concurrent mints do not contend on it. The mint is a fresh keypair
+    /// signing the transaction and the mint is counted in the client chosen
+    /// ['mint_counter', minter, shard] account instead of the minter count.
+    /// Only free mints run in parallel: paid ones still write to the treasury
+    /// or the payout recipients, which serializes them.
+    /// Remaining accounts are passed as for `mint_with_collection`.
+    pub fn mint_unordered<'info>(
+        ctx: Context<'_, '_, '_, 'info, MintUnordered<'info>>,
+        record_bump: u8,
+        shard: u8,
+        name: String,
+        symbol: String,
+        uri: String,
+        seller_fee_basis_points: u16,
+        is_mutable: bool,
+        collection: Pubkey,
+    ) -> Result<()> {
+        if ctx.accounts.collection_mint.key() != collection {
+            return Err(MinterError::MismatchedCollection.into());
+        }
+        if !ctx.accounts.mint.is_signer {
+            return Err(MinterError::MintNotSigner.into());
+        }
+
+        let minter = &ctx.accounts.minter;
+        if !minter.unordered {
+            return Err(MinterError::UnorderedDisabled.into());
+        }
+        // these write accounts shared by every mint, or need ordered indices
+        if minter.auction
+            || minter.escrow.is_some()
+            || minter.template.is_some()
+            || minter.hidden.is_some()
+            || minter.random_indices
+            || ctx.accounts.collection.collection_details.is_some()
+        {
+            return Err(MinterError::UnorderedUnsupported.into());
+        }
+
+        let remaining_accounts = &mut ctx.remaining_accounts.iter();
+
+        let phase = if minter.phases > 0 {
+            Some(enter_phase(
+                &minter.key(),
+                &ctx.accounts.payer.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                &ctx.accounts.rent.to_account_info(),
+                remaining_accounts,
+            )?)
+        } else {
+            None
+        };
+        let price = phase.as_ref().map_or(minter.price, |phase| phase.price);
+        let gate = match &phase {
+            Some(phase) => &phase.gate,
+            None => &minter.gate,
+        };
+
+        validate_metadata(&name, &symbol, &uri, seller_fee_basis_points)?;
+        minter.check_mint_limits(&uri, seller_fee_basis_points)?;
+
+        settle_mint(
+            &minter.key(),
+            minter,
+            &ctx.accounts.mint.key(),
+            gate.as_ref(),
+            price,
+            false,
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.rent.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            &ctx.accounts.token_metadata_program.to_account_info(),
+            remaining_accounts,
+        )?;
+
+        let minter_seeds = &[MINTER_KEY_V2, minter.owner.as_ref(), &[minter.bump]];
+
+        // the mint signs the transaction, no seeds needed to create it
+        create_or_allocate_account_raw(
+            *ctx.accounts.token_program.key,
+            &ctx.accounts.mint.to_account_info(),
+            &ctx.accounts.rent.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            Mint::LEN,
+            &[],
+        )?;
+
+        token::initialize_mint(
+            ctx.accounts.into_initialize_mint_context(),
+            0,
+            &minter.key(),
+            None,
+        )?;
+
+        associated_token::create(
+            ctx.accounts
+                .into_create_associated_token_context(&ctx.accounts.payer),
+        )?;
+
+        token::mint_to(
+            ctx.accounts
+                .into_token_mint_to_context(&minter.to_account_info())
+                .with_signer(&[&minter_seeds[..]]),
+            1,
+        )?;
+
+        token_metadata::create_metadata_account_v2(
+            ctx.accounts
+                .into_create_metadata_context(&ctx.accounts.payer, &minter.to_account_info())
+                .with_signer(&[&minter_seeds[..]]),
+            name,
+            symbol,
+            uri,
+            Some(vec![
+                token_metadata::state::Creator {
+                    address: *ctx.accounts.payer.key,
+                    verified: false,
+                    share: 98,
+                },
+                token_metadata::state::Creator {
+                    address: minter.key(),
+                    verified: true,
+                    share: 2,
+                },
+            ]),
+            seller_fee_basis_points,
+            true,
+            is_mutable,
+            Some(token_metadata::state::Collection {
+                key: collection,
+                verified: false,
+            }),
+            None,
+        )?;
+
+        token_metadata::create_master_edition_v3(
+            ctx.accounts
+                .into_create_master_edition_context(&ctx.accounts.payer, &minter.to_account_info())
+                .with_signer(&[&minter_seeds[..]]),
+            Some(0),
+        )?;
+
+        token_metadata::sign_metadata(
+            ctx.accounts.into_sign_metadata_context(&ctx.accounts.payer),
+        )?;
+
+        // sized collections are rejected above
+        verify_collection_item(
+            false,
+            ctx.accounts
+                .into_verify_collection_context()
+                .with_remaining_accounts(vec![ctx
+                    .accounts
+                    .collection_authority_record
+                    .to_account_info()
+                    .clone()])
+                .with_signer(&[&minter_seeds[..]]),
+        )?;
+        flag_primary_sale(
+            minter,
+            price,
+            ctx.accounts.into_update_primary_sale_happened_context(),
+        )?;
+
+        let index = increment_mint_counter(
+            &minter.key(),
+            shard,
+            &ctx.accounts.counter.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.rent.to_account_info(),
+        )?;
+
+        let clock = Clock::get()?;
+        create_mint_record(
+            &ctx.accounts.mint_record.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.rent.to_account_info(),
+            MintRecord {
+                mint: ctx.accounts.mint.key(),
+                minter: minter.key(),
+                index,
+                metadata_index: index,
+                shard: Some(shard),
+                recipient: ctx.accounts.recipient.key(),
+                slot: clock.slot,
+                timestamp: clock.unix_timestamp,
+                bump: record_bump,
+            },
+        )
+    }
+
+    /// Mints a Token-2022 NFT: a zero decimals mint with a supply of one and
+    /// no mint authority left. Token Metadata does not take Token-2022 mints,
+    /// so it has no metadata, and minters with metadata settings, or paid
+    /// mints that would flag the primary sale, are rejected. Proceeds
+    /// accounts are passed as for `mint_with_collection`.
+    pub fn mint_token_2022<'info>(
+        ctx: Context<'_, '_, '_, 'info, MintToken2022<'info>>,
+        mint_bump: u8,
+        record_bump: u8,
+    ) -> Result<()> {
+        let remaining_accounts = &mut ctx.remaining_accounts.iter();
+
+        let minter = &ctx.accounts.minter;
+        let config = minter
+            .token_2022
+            .as_ref()
+            .ok_or(MinterError::Token2022Disabled)?;
+        if minter.auction
+            || minter.escrow.is_some()
+            || minter.phases > 0
+            || minter.gate.is_some()
+            || minter.burn_to_mint.is_some()
+            || minter.random_indices
+            || minter.referral_basis_points.is_some()
+        {
+            return Err(MinterError::Token2022Unsupported.into());
+        }
+        // the primary sale flag lives in the metadata too
+        if minter.template.is_some()
+            || minter.hidden.is_some()
+            || minter.max_seller_fee_basis_points.is_some()
+            || minter.uri_prefix.is_some()
+            || (minter.price > 0 && !minter.skip_primary_sale)
+        {
+            return Err(MinterError::Token2022MetadataUnsupported.into());
+        }
+
+        if minter.price > 0 {
+            let count = minter.payout_recipients().len() + minter.payment_mint.is_some() as usize;
+            let accounts = (0..count)
+                .map(|_| next_account_info(remaining_accounts).cloned())
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            distribute_proceeds(
+                minter,
+                minter.price,
+                &ctx.accounts.payer.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                &ctx.accounts.token_program.to_account_info(),
+                &accounts,
+            )?;
+        }
+        emit!(MintPriceEvent {
+            minter: minter.key(),
+            mint: ctx.accounts.mint.key(),
+            payer: ctx.accounts.payer.key(),
+            price_paid: minter.price,
+        });
+
+        let minter_seeds = &[MINTER_KEY_V2, minter.owner.as_ref(), &[minter.bump]];
+
+        // check that mint address is a valid program derived address
+        let minter_count_seed = mint_index_seed(minter.count);
+        let mint_seeds = &[MINT_KEY_V2, &minter_count_seed[..], &[mint_bump]];
+        let mint_addr = Pubkey::create_program_address(&mint_seeds[..], ctx.program_id)
+            .map_err(|_| MinterError::InvalidMintAddress)?;
+        if ctx.accounts.mint.key != &mint_addr {
+            return Err(MinterError::InvalidMintAddress.into());
+        }
+        create_or_allocate_account_raw(
+            spl_token_2022::ID,
+            &ctx.accounts.mint.to_account_info(),
+            &ctx.accounts.rent.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            config.mint_len(),
+            &[&mint_seeds[..]],
+        )?;
+
+        // extensions are initialized before the mint itself
+        if config.non_transferable {
+            invoke(
+                &spl_token_2022::instruction::initialize_non_transferable_mint(
+                    &spl_token_2022::ID,
+                    &mint_addr,
+                )?,
+                &[ctx.accounts.mint.to_account_info()],
+            )?;
+        }
+        invoke(
+            &spl_token_2022::instruction::initialize_mint(
+                &spl_token_2022::ID,
+                &mint_addr,
+                &minter.key(),
+                None,
+                0,
+            )?,
+            &[
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.rent.to_account_info(),
+            ],
+        )?;
+
+        // the associated token program derives Token-2022 accounts from the
+        // token program it is given
+        invoke(
+            &Instruction {
+                program_id: *ctx.accounts.associated_token_program.key,
+                accounts: vec![
+                    AccountMeta::new(*ctx.accounts.payer.key, true),
+                    AccountMeta::new(*ctx.accounts.associated_token_account.key, false),
+                    AccountMeta::new_readonly(*ctx.accounts.payer.key, false),
+                    AccountMeta::new_readonly(mint_addr, false),
+                    AccountMeta::new_readonly(*ctx.accounts.system_program.key, false),
+                    AccountMeta::new_readonly(spl_token_2022::ID, false),
+                    AccountMeta::new_readonly(ctx.accounts.rent.key(), false),
+                ],
+                data: vec![],
+            },
+            &[
+                ctx.accounts.payer.to_account_info(),
+                ctx.accounts.associated_token_account.to_account_info(),
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+                ctx.accounts.token_2022_program.to_account_info(),
+                ctx.accounts.rent.to_account_info(),
+            ],
+        )?;
+
+        invoke_signed(
+            &spl_token_2022::instruction::mint_to(
+                &spl_token_2022::ID,
+                &mint_addr,
+                ctx.accounts.associated_token_account.key,
+                &minter.key(),
+                &[],
+                1,
+            )?,
+            &[
+                ctx.accounts.mint.to_account_info(),
+                ctx.accounts.associated_token_account.to_account_info(),
+                minter.to_account_info(),
+            ],
+            &[&minter_seeds[..]],
+        )?;
+
+        // without a master edition the supply is fixed by dropping the authority
+        invoke_signed(
+            &spl_token_2022::instruction::set_authority(
+                &spl_token_2022::ID,
+                &mint_addr,
+                None,
+                spl_token_2022::instruction::AuthorityType::MintTokens,
+                &minter.key(),
+                &[],
+            )?,
+            &[
+                ctx.accounts.mint.to_account_info(),
+                minter.to_account_info(),
+            ],
+            &[&minter_seeds[..]],
+        )?;
+
+        let clock = Clock::get()?;
+        create_mint_record(
+            &ctx.accounts.mint_record.to_account_info(),
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.rent.to_account_info(),
+            MintRecord {
+                mint: mint_addr,
+                minter: minter.key(),
+                index: minter.count,
+                metadata_index: minter.count,
+                shard: None,
+                recipient: ctx.accounts.payer.key(),
+                slot: clock.slot,
+                timestamp: clock.unix_timestamp,
+                bump: record_bump,
+            },
+        )?;
+
+        ctx.accounts.minter.reload()?;
+        let minter = &mut ctx.accounts.minter;
+        minter.count = minter
+            .count
+            .checked_add(1)
+            .ok_or(MinterError::CounterOverflow)?;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeMinter<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(init,
+        seeds = [b"minter", authority.key.as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + MinterAccount::LEN,
+    )]
+    pub minter: Account<'info, MinterAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeMinterV2<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(init,
+        seeds = [b"minter_v2", authority.key.as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + MinterAccountV2::LEN,
+    )]
+    pub minter: Account<'info, MinterAccountV2>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateMinter<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: ['minter', authority], deserialized once it has been grown
+    #[account(mut,
+        seeds = [b"minter", authority.key.as_ref()],
+        bump,
+        owner = crate::ID,
+    )]
+    pub minter: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateMinterV2<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: ['minter_v2', authority], deserialized once it has been grown
+    #[account(mut,
+        seeds = [b"minter_v2", authority.key.as_ref()],
+        bump,
+        owner = crate::ID,
+    )]
+    pub minter: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+#[derive(Default, Debug)]
+#[repr(C)]
+pub struct MinterAccountV2 {
+    pub owner: Pubkey,
+    /// Counter of layout version 0, carried over to `count` on migration.
+    pub legacy_count: u32,
+    /// Last bound collection, every bound one has a `CollectionRegistry`.
+    pub collection: Option<Pubkey>,
+    pub bump: u8,
+    pub template: Option<MetadataTemplate>,
+    pub hidden: Option<HiddenSettings>,
+    pub random_indices: bool,
+    pub max_seller_fee_basis_points: Option<u16>,
+    pub uri_prefix: Option<String>,
+    pub price: u64,
+    pub treasury: Pubkey,
+    pub skip_primary_sale: bool,
+    pub gate: Option<TokenGate>,
+    pub burn_to_mint: Option<Pubkey>,
+    pub version: u8,
+    pub count: u64,
+    /// Mints are priced by the minter's `Auction` instead of `price`.
+    pub auction: bool,
+    /// Number of `Phase`s, mints must go through one of them if any.
+    pub phases: u8,
+    /// Share of paid mint prices that goes to the referrer, if any.
+    pub referral_basis_points: Option<u16>,
+    /// Recipients of mint proceeds, the treasury alone if empty.
+    pub payouts: Vec<Payout>,
+    /// Mint of the SPL token prices are paid in, lamports if `None`.
+    pub payment_mint: Option<Pubkey>,
+    /// Proceeds are held in the minter's `Vault` until the drop succeeds.
+    pub escrow: Option<EscrowConfig>,
+    /// Mints go through `mint_token_2022` with these extensions.
+    pub token_2022: Option<Token2022Config>,
+    /// `mint_unordered` is allowed.
+    pub unordered: bool,
+}
+
+impl MinterAccountV2 {
+    /// Reads a minter written before layout version 1, from the account
+    /// data with its discriminator. That layout only had `owner`, the u32
+    /// count and `collection`, the other fields keep their defaults.
+    pub fn deserialize_legacy(data: &[u8]) -> Result<Self> {
+        let buf = &mut &data[8..];
+        Ok(MinterAccountV2 {
+            owner: AnchorDeserialize::deserialize(buf)?,
+            legacy_count: AnchorDeserialize::deserialize(buf)?,
+            collection: AnchorDeserialize::deserialize(buf)?,
+            ..Default::default()
+        })
+    }
+
+    pub const LEN: usize = 32 // owner
+        + 4 // count
+        + 33 // collection
+        + 1 // bump
+        + 1 + MetadataTemplate::LEN // template
+        + 1 + HiddenSettings::LEN // hidden
+        + 1 // random_indices
+        + 3 // max_seller_fee_basis_points
+        + 1 + 4 + token_metadata::state::MAX_URI_LENGTH // uri_prefix
+        + 8 // price
+        + 32 // treasury
+        + 1 // skip_primary_sale
+        + 1 + TokenGate::LEN // gate
+        + 33 // burn_to_mint
+        + 1 // version
+        + 8 // count
+        + 1 // auction
+        + 1 // phases
+        + 3 // referral_basis_points
+        + 4 + MAX_PAYOUTS * Payout::LEN // payouts
+        + 33 // payment_mint
+        + 1 + EscrowConfig::LEN // escrow
+        + 1 + Token2022Config::LEN // token_2022
+        + 1; // unordered
+
+    /// Recipients the mint proceeds are split across.
+    pub fn payout_recipients(&self) -> Vec<Payout> {
+        if self.payouts.is_empty() {
+            vec![Payout {
+                recipient: self.treasury,
+                basis_points: MAX_BASIS_POINTS,
+            }]
+        } else {
+            self.payouts.clone()
+        }
+    }
+
+    pub fn check_mint_limits(&self, uri: &str, seller_fee_basis_points: u16) -> Result<()> {
+        if let Some(max) = self.max_seller_fee_basis_points {
+            if seller_fee_basis_points > max {
+                return Err(MinterError::SellerFeeBasisPointsTooHigh.into());
+            }
+        }
+        if let Some(prefix) = &self.uri_prefix {
+            if !uri.starts_with(prefix.as_str()) {
+                return Err(MinterError::UriPrefixNotAllowed.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Fills the metadata at `index` from the hidden settings or the
+    /// template. Fields they leave open are taken from the client as is.
+    pub fn resolve_metadata(
+        &self,
+        index: u64,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<(String, String, String)> {
+        let enforced = matches!(&self.template, Some(template) if template.enforced);
+        if enforced && (!name.is_empty() || !symbol.is_empty() || !uri.is_empty()) {
+            return Err(MinterError::ClientMetadataForbidden.into());
+        }
+
+        let (name, uri) = match (&self.hidden, &self.template) {
+            (Some(hidden), _) => {
+                // the final name and uri are only set on reveal
+                if !name.is_empty() || !uri.is_empty() {
+                    return Err(MinterError::ClientMetadataForbidden.into());
+                }
+                (
+                    format!("{} #{}", hidden.name_prefix, index),
+                    hidden.uri.clone(),
+                )
+            }
+            (None, Some(template)) => (
+                if name.is_empty() {
+                    format!("{} #{}", template.name_prefix, index)
+                } else {
+                    name
+                },
+                if uri.is_empty() {
+                    format!("{}/{}.json", template.base_uri, index)
+                } else {
+                    uri
+                },
+            ),
+            (None, None) => (name, uri),
+        };
+        let symbol = match &self.template {
+            Some(template) if symbol.is_empty() => template.symbol.clone(),
+            _ => symbol,
+        };
+        Ok((name, symbol, uri))
+    }
+}
+
+/// A [`MinterAccountV2`] in the current layout. Minters written with an older
+/// layout fail to load with `OutdatedMinterLayout` rather than a
+/// deserialization error, `migrate_minter_v2` brings them up to date.
+#[derive(Clone, Default, Debug)]
+pub struct CurrentMinterV2(MinterAccountV2);
+
+impl AccountDeserialize for CurrentMinterV2 {
+    fn try_deserialize(buf: &mut &[u8]) -> Result<Self> {
+        if buf.len() < 8
+            || buf[..8] != <MinterAccountV2 as anchor_lang::Discriminator>::discriminator()
+        {
+            return Err(ErrorCode::AccountDiscriminatorMismatch.into());
+        }
+        Self::try_deserialize_unchecked(buf)
+    }
+
+    fn try_deserialize_unchecked(buf: &mut &[u8]) -> Result<Self> {
+        let minter = MinterAccountV2::try_deserialize_unchecked(buf)
+            .map_err(|_| MinterError::OutdatedMinterLayout)?;
+        if minter.version != MINTER_V2_LAYOUT_VERSION {
+            return Err(MinterError::OutdatedMinterLayout.into());
+        }
+        Ok(Self(minter))
+    }
+}
+
+impl AccountSerialize for CurrentMinterV2 {
+    fn try_serialize<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+        self.0.try_serialize(writer)
+    }
+}
+
+impl Owner for CurrentMinterV2 {
+    fn owner() -> Pubkey {
+        MinterAccountV2::owner()
+    }
+}
+
+impl std::ops::Deref for CurrentMinterV2 {
+    type Target = MinterAccountV2;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for CurrentMinterV2 {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// On-chain name and uri template for NFTs issued by a v2 minter through
+/// `mint_with_collection`. The v1 `mint_for` path does not use it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug)]
+pub struct MetadataTemplate {
+    /// Name becomes "{name_prefix} #{count}".
+    pub name_prefix: String,
+    pub symbol: String,
+    /// Uri becomes "{base_uri}/{count}.json".
+    pub base_uri: String,
+    /// Reject mints that pass their own name, symbol or uri.
+    pub enforced: bool,
+}
+
+impl MetadataTemplate {
+    // room for indices up to u32::MAX, " #4294967295" and "/4294967295.json",
+    // names and uris of larger ones fail `validate_metadata`
+    pub const MAX_NAME_PREFIX_LEN: usize = token_metadata::state::MAX_NAME_LENGTH - 12;
+    pub const MAX_BASE_URI_LEN: usize = token_metadata::state::MAX_URI_LENGTH - 16;
+    pub const LEN: usize = 4
+        + Self::MAX_NAME_PREFIX_LEN
+        + 4
+        + token_metadata::state::MAX_SYMBOL_LENGTH
+        + 4
+        + Self::MAX_BASE_URI_LEN
+        + 1;
+
+    pub fn validate(&self) -> Result<()> {
+        if self.name_prefix.len() > Self::MAX_NAME_PREFIX_LEN
+            || self.symbol.len() > token_metadata::state::MAX_SYMBOL_LENGTH
+            || self.base_uri.len() > Self::MAX_BASE_URI_LEN
+        {
+            return Err(MinterError::MetadataTemplateTooLong.into());
+        }
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(Default, Debug)]
+#[repr(C)]
+pub struct MinterAccount {
+    pub owner: Pubkey,
+    /// Counter of layout version 0, carried over to `count` on migration.
+    pub legacy_count: u32,
+    pub bump: u8,
+    pub version: u8,
+    pub count: u64,
+}
+
+impl MinterAccount {
+    pub const LEN: usize = 32 + 4 + 1 + 1 + 8;
+}
+
+/// Placeholder metadata for blind mints, replaced by `reveal`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug)]
+pub struct HiddenSettings {
+    /// Name becomes "{name_prefix} #{count}".
+    pub name_prefix: String,
+    pub uri: String,
+    /// Hash of the final metadata list, committed before minting starts.
+    pub hash: [u8; 32],
+}
+
+impl HiddenSettings {
+    pub const LEN: usize =
+        4 + MetadataTemplate::MAX_NAME_PREFIX_LEN + 4 + token_metadata::state::MAX_URI_LENGTH + 32;
+
+    pub fn validate(&self) -> Result<()> {
+        if self.name_prefix.len() > MetadataTemplate::MAX_NAME_PREFIX_LEN
+            || self.uri.len() > token_metadata::state::MAX_URI_LENGTH
+        {
+            return Err(MinterError::MetadataTemplateTooLong.into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct RevealItem {
+    pub index: u32,
+    pub mint_bump: u8,
+    pub name: String,
+    pub uri: String,
+}
+
+/// Tracks which indices of a hidden drop have been revealed.
+#[account]
+#[derive(Default, Debug)]
+pub struct RevealRecord {
+    pub minter: Pubkey,
+    pub bump: u8,
+    pub size: u32,
+    pub revealed: u32,
+    pub bitmap: Vec<u8>,
+}
+
+impl RevealRecord {
+    // usize::div_ceil is newer than the rustc of the BPF toolchain
+    #[allow(clippy::manual_div_ceil)]
+    pub fn bitmap_len(size: u32) -> usize {
+        (size as usize + 7) / 8
+    }
+
+    pub fn space(size: u32) -> usize {
+        8 + 32 + 1 + 4 + 4 + 4 + Self::bitmap_len(size)
+    }
+}
+
+/// Provenance of an NFT issued by a minter, stored at ['mint_record', mint].
+#[account]
+#[derive(Default, Debug)]
+pub struct MintRecord {
+    pub mint: Pubkey,
+    pub minter: Pubkey,
+    /// Value of the minter count the NFT was minted at, or of the counter of
+    /// `shard` for unordered mints. Only the pair is unique across shards.
+    pub index: u64,
+    /// Index the metadata was resolved for, the drawn one on minters with
+    /// random indices and `index` otherwise.
+    pub metadata_index: u64,
+    /// Counter shard of unordered mints.
+    pub shard: Option<u8>,
+    pub recipient: Pubkey,
+    pub slot: u64,
+    pub timestamp: i64,
+    pub bump: u8,
+}
+
+impl MintRecord {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 2 + 32 + 8 + 8 + 1;
+
+    pub fn address(mint: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[MINT_RECORD_KEY, mint.as_ref()], &ID)
+    }
+}
+
+/// Shard of the count of unordered mints, the total is the sum over shards.
+#[account]
+#[derive(Default, Debug)]
+pub struct MintCounter {
+    pub minter: Pubkey,
+    pub shard: u8,
+    pub count: u64,
+    pub bump: u8,
+}
+
+impl MintCounter {
+    pub const LEN: usize = 32 + 1 + 8 + 1;
+}
+
+/// Fungible or semi-fungible asset issued by a minter.
+#[account]
+#[derive(Default, Debug)]
+pub struct FungibleAsset {
+    pub minter: Pubkey,
+    pub mint: Pubkey,
+    pub id: u32,
+    pub max_supply: Option<u64>,
+    pub bump: u8,
+}
+
+impl FungibleAsset {
+    pub const LEN: usize = 32 + 32 + 4 + 9 + 1;
+}
+
+/// A collection bound to a minter, one per collection.
+#[account]
+#[derive(Default, Debug)]
+pub struct CollectionRegistry {
+    pub minter: Pubkey,
+    pub collection: Pubkey,
+    pub bump: u8,
+}
+
+impl CollectionRegistry {
+    pub const LEN: usize = 32 + 32 + 1;
+}
+
+/// Token the payer must hold to mint.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub enum TokenGate {
+    /// An NFT of a verified collection, optionally usable a limited number of
+    /// times per NFT.
+    Collection {
+        collection: Pubkey,
+        max_uses: Option<u32>,
+    },
+    /// At least `min_amount` of an SPL mint.
+    Mint { mint: Pubkey, min_amount: u64 },
+}
+
+impl TokenGate {
+    pub const LEN: usize = 1 + 32 + 8;
+}
+
+/// Number of mints made with one gate NFT.
+#[account]
+#[derive(Default, Debug)]
+pub struct GateUsage {
+    pub minter: Pubkey,
+    pub gate_mint: Pubkey,
+    pub uses: u32,
+    pub bump: u8,
+}
+
+impl GateUsage {
+    pub const LEN: usize = 32 + 32 + 4 + 1;
+}
+
+/// Mint window with its own price, wallet limit and gate.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug)]
+pub struct PhaseConfig {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub price: u64,
+    /// Mints allowed per wallet during the phase.
+    pub wallet_limit: Option<u32>,
+    pub gate: Option<TokenGate>,
+}
+
+impl PhaseConfig {
+    pub const LEN: usize = 8 + 8 + 8 + 5 + 1 + TokenGate::LEN;
+
+    pub fn validate(&self, now: i64) -> Result<()> {
+        if self.start_time <= now || self.end_time <= self.start_time {
+            return Err(MinterError::InvalidPhaseConfig.into());
+        }
+        Ok(())
+    }
+
+    pub fn is_active(&self, now: i64) -> bool {
+        self.start_time <= now && now < self.end_time
+    }
+
+    pub fn overlaps(&self, other: &PhaseConfig) -> bool {
+        self.start_time < other.end_time && other.start_time < self.end_time
+    }
+}
+
+#[account]
+#[derive(Default, Debug)]
+pub struct Phase {
+    pub minter: Pubkey,
+    pub id: u8,
+    pub bump: u8,
+    pub config: PhaseConfig,
+}
+
+impl Phase {
+    pub const LEN: usize = 32 + 1 + 1 + PhaseConfig::LEN;
+}
+
+/// Number of mints a wallet made during a phase.
+#[account]
+#[derive(Default, Debug)]
+pub struct WalletMints {
+    pub phase: Pubkey,
+    pub wallet: Pubkey,
+    pub count: u32,
+    pub bump: u8,
+}
+
+impl WalletMints {
+    pub const LEN: usize = 32 + 32 + 4 + 1;
+}
+
+/// Price falling from `start_price` by `step` every `interval` seconds
+/// after `start_time`, down to `floor_price`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug)]
+pub struct DutchAuction {
+    pub start_price: u64,
+    pub floor_price: u64,
+    pub start_time: i64,
+    pub interval: i64,
+    pub step: u64,
+    /// Buyers can claim back what they paid above the clearing price.
+    pub rebate: bool,
+}
+
+impl DutchAuction {
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 8 + 1;
+
+    pub fn validate(&self) -> Result<()> {
+        if self.interval <= 0 || self.floor_price > self.start_price {
+            return Err(MinterError::InvalidAuctionConfig.into());
+        }
+        Ok(())
+    }
+
+    pub fn price_at(&self, timestamp: i64) -> u64 {
+        let steps = (timestamp.saturating_sub(self.start_time) / self.interval).max(0) as u64;
+        self.start_price
+            .saturating_sub(steps.saturating_mul(self.step))
+            .max(self.floor_price)
+    }
+}
+
+#[account]
+#[derive(Default, Debug)]
+pub struct Auction {
+    pub minter: Pubkey,
+    pub bump: u8,
+    pub config: DutchAuction,
+    pub sold: u64,
+    pub total_paid: u64,
+    pub last_price: u64,
+    /// Set once the auction is settled.
+    pub clearing_price: Option<u64>,
+    /// Proceeds sent to the treasury in rebate mode.
+    pub withdrawn: u64,
+}
+
+impl Auction {
+    pub const LEN: usize = 32 + 1 + DutchAuction::LEN + 8 + 8 + 8 + 9 + 8;
+}
+
+/// What a buyer paid in a rebate mode auction.
+#[account]
+#[derive(Default, Debug)]
+pub struct AuctionReceipt {
+    pub auction: Pubkey,
+    pub buyer: Pubkey,
+    pub count: u64,
+    pub total_paid: u64,
+    pub bump: u8,
+}
+
+impl AuctionReceipt {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 1;
+}
+
+/// Extensions of the mints of a Token-2022 minter.
+///
+/// The metadata-pointer extension is not supported yet: it needs
+/// spl-token-2022 0.8, which requires Solana 1.16 and a newer Anchor than
+/// this program builds with.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug)]
+pub struct Token2022Config {
+    /// Holders cannot transfer the NFT, only burn it.
+    pub non_transferable: bool,
+}
+
+impl Token2022Config {
+    pub const LEN: usize = 1;
+
+    pub fn extensions(&self) -> Vec<ExtensionType> {
+        let mut extensions = vec![];
+        if self.non_transferable {
+            extensions.push(ExtensionType::NonTransferable);
+        }
+        extensions
+    }
+
+    /// Size of a mint account with the extensions.
+    pub fn mint_len(&self) -> usize {
+        ExtensionType::get_account_len::<spl_token_2022::state::Mint>(&self.extensions())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug)]
+pub struct EscrowConfig {
+    /// Escrowed mints needed for the drop to succeed.
+    pub threshold: u64,
+    pub deadline: i64,
+}
+
+impl EscrowConfig {
+    pub const LEN: usize = 8 + 8;
+}
+
+/// Holds the escrowed proceeds of a minter.
+#[account]
+#[derive(Default, Debug)]
+pub struct Vault {
+    pub minter: Pubkey,
+    pub bump: u8,
+    pub sold: u64,
+}
+
+impl Vault {
+    pub const LEN: usize = 32 + 1 + 8;
+}
+
+/// What was paid into the vault for a mint.
+#[account]
+#[derive(Default, Debug)]
+pub struct PaymentReceipt {
+    pub mint: Pubkey,
+    pub minter: Pubkey,
+    pub payer: Pubkey,
+    pub amount: u64,
+    /// Deadline of the escrow paid into, so that receipts of an earlier
+    /// drop cannot claim refunds from a later one.
+    pub deadline: i64,
+    pub bump: u8,
+}
+
+impl PaymentReceipt {
+    pub const LEN: usize = 32 + 32 + 32 + 8 + 8 + 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug)]
+pub struct Payout {
+    pub recipient: Pubkey,
+    pub basis_points: u16,
+}
+
+impl Payout {
+    pub const LEN: usize = 32 + 2;
+}
+
+/// Mints referred by a referrer and what it earned from them.
+#[account]
+#[derive(Default, Debug)]
+pub struct ReferrerStats {
+    pub minter: Pubkey,
+    pub referrer: Pubkey,
+    pub mints: u64,
+    pub earnings: u64,
+    pub bump: u8,
+}
+
+impl ReferrerStats {
+    pub const LEN: usize = 32 + 32 + 8 + 8 + 1;
+}
+
+#[event]
+pub struct MintPriceEvent {
+    pub minter: Pubkey,
+    pub mint: Pubkey,
+    pub payer: Pubkey,
+    pub price_paid: u64,
+}
+
+#[event]
+pub struct AuctionPriceEvent {
+    pub minter: Pubkey,
+    pub price: u64,
+    pub timestamp: i64,
+}
+
+/// Metadata indices already drawn by a minter in `random_indices` mode.
+#[account]
+#[derive(Default, Debug)]
+pub struct IndexPool {
+    pub minter: Pubkey,
+    pub bump: u8,
+    pub size: u32,
+    pub taken: u32,
+    pub bitmap: Vec<u8>,
+}
+
+impl IndexPool {
+    pub fn space(size: u32) -> usize {
+        8 + 32 + 1 + 4 + 4 + 4 + RevealRecord::bitmap_len(size)
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetMetadataTemplate<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+}
+
+#[derive(Accounts)]
+pub struct SetHiddenSettings<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+}
+
+#[derive(Accounts)]
+#[instruction(size: u32)]
+pub struct InitializeRevealRecord<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(init,
+        seeds = [REVEAL_KEY, minter.key().as_ref()],
+        bump,
+        payer = authority,
+        space = RevealRecord::space(size),
+    )]
+    pub reveal_record: Account<'info, RevealRecord>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Reveal<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(mut,
+        seeds = [REVEAL_KEY, minter.key().as_ref()],
+        bump = reveal_record.bump,
+    )]
+    pub reveal_record: Account<'info, RevealRecord>,
+
+    pub token_metadata_program: Program<'info, TokenMetadata>,
+}
+
+impl<'info> Reveal<'info> {
+    pub fn into_update_metadata_context(
+        &self,
+        metadata: &AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::UpdateMetadataAccountsV2<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::UpdateMetadataAccountsV2 {
+            metadata: metadata.clone(),
+            update_authority: self.minter.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(size: u32)]
+pub struct InitializeIndexPool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(init,
+        seeds = [INDEX_POOL_KEY, minter.key().as_ref()],
+        bump,
+        payer = authority,
+        space = IndexPool::space(size),
+    )]
+    pub index_pool: Account<'info, IndexPool>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMintLimits<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+}
+
+#[derive(Accounts)]
+pub struct BurnNft<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// CHECK: v1 or v2 minter, checked against the verified creators
+    #[account(owner = crate::ID)]
+    pub minter: UncheckedAccount<'info>,
+
+    #[account(mut, has_one = mint)]
+    pub metadata: Box<Account<'info, token_metadata::MetadataAccount>>,
+
+    /// CHECK: ['metadata', metadata::ID, mint, 'edition']
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(mut,
+        token::mint = mint,
+        token::authority = owner,
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub token_metadata_program: Program<'info, TokenMetadata>,
+}
+
+impl<'info> BurnNft<'info> {
+    pub fn into_burn_nft_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::BurnNft<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::BurnNft {
+            metadata: self.metadata.to_account_info(),
+            owner: self.owner.to_account_info(),
+            mint: self.mint.to_account_info(),
+            token: self.token_account.to_account_info(),
+            edition: self.master_edition.to_account_info(),
+            spl_token: self.token_program.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetPrice<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+}
+
+#[derive(Accounts)]
+pub struct SetTokenGate<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+}
+
+#[derive(Accounts)]
+pub struct SetBurnToMint<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: u8)]
+pub struct AddPhase<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(init,
+        seeds = [b"phase", minter.key().as_ref(), &[id]],
+        bump,
+        payer = authority,
+        space = 8 + Phase::LEN,
+    )]
+    pub phase: Account<'info, Phase>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EditPhase<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(mut,
+        seeds = [b"phase", minter.key().as_ref(), &[phase.id]],
+        bump = phase.bump,
+        constraint = phase.config.start_time > Clock::get()?.unix_timestamp @ MinterError::PhaseAlreadyStarted,
+    )]
+    pub phase: Account<'info, Phase>,
+}
+
+#[derive(Accounts)]
+pub struct RemovePhase<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(mut,
+        seeds = [b"phase", minter.key().as_ref(), &[phase.id]],
+        bump = phase.bump,
+        constraint = phase.config.start_time > Clock::get()?.unix_timestamp @ MinterError::PhaseAlreadyStarted,
+        close = authority,
+    )]
+    pub phase: Account<'info, Phase>,
+}
+
+#[derive(Accounts)]
+pub struct SetPayouts<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+}
+
+#[derive(Accounts)]
+pub struct SetEscrow<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(init,
+        seeds = [b"vault", minter.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + Vault::LEN,
+    )]
+    pub vault: Account<'info, Vault>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateEscrow<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(
+        seeds = [b"vault", minter.key().as_ref()],
+        bump = vault.bump,
+    )]
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+pub struct CloseEscrow<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(mut,
+        seeds = [b"vault", minter.key().as_ref()],
+        bump = vault.bump,
+        close = authority,
+    )]
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawEscrow<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(mut,
+        seeds = [b"vault", minter.key().as_ref()],
+        bump = vault.bump,
+    )]
+    pub vault: Account<'info, Vault>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRefund<'info> {
+    #[account(mut)]
+    pub holder: Signer<'info>,
+
+    #[account(
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(mut,
+        seeds = [b"vault", minter.key().as_ref()],
+        bump = vault.bump,
+    )]
+    pub vault: Account<'info, Vault>,
+
+    #[account(mut,
+        seeds = [b"payment_receipt", mint.key().as_ref()],
+        bump = receipt.bump,
+        has_one = mint @ MinterError::InvalidPaymentReceipt,
+        has_one = minter @ MinterError::InvalidPaymentReceipt,
+        close = holder,
+    )]
+    pub receipt: Account<'info, PaymentReceipt>,
+
+    #[account(mut, has_one = mint)]
+    pub metadata: Box<Account<'info, token_metadata::MetadataAccount>>,
+
+    /// CHECK: ['metadata', metadata::ID, mint, 'edition']
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(mut,
+        token::mint = mint,
+        token::authority = holder,
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub token_metadata_program: Program<'info, TokenMetadata>,
+}
+
+impl<'info> ClaimRefund<'info> {
+    pub fn into_burn_nft_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::BurnNft<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::BurnNft {
+            metadata: self.metadata.to_account_info(),
+            owner: self.holder.to_account_info(),
+            mint: self.mint.to_account_info(),
+            token: self.token_account.to_account_info(),
+            edition: self.master_edition.to_account_info(),
+            spl_token: self.token_program.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetToken2022<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+}
+
+#[derive(Accounts)]
+pub struct MintToken2022<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    /// CHECK: ['mint_record', mint], created by the instruction
+    #[account(mut)]
+    pub mint_record: UncheckedAccount<'info>,
+
+    /// CHECK: ['mint_v2', count.to_le_bytes()]
+    #[account(mut)]
+    pub mint: UncheckedAccount<'info>,
+
+    /// CHECK: [payer, spl_token_2022::ID, mint]
+    #[account(mut)]
+    pub associated_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: Token-2022 program
+    #[account(address = spl_token_2022::ID)]
+    pub token_2022_program: UncheckedAccount<'info>,
+
+    /// SPL token program, for proceeds paid in a payment mint
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SetUnordered<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+}
+
+#[derive(Accounts)]
+pub struct SetReferral<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+}
+
+#[derive(Accounts)]
+pub struct StartAuction<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(init,
+        seeds = [b"auction", minter.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + Auction::LEN,
+    )]
+    pub auction: Account<'info, Auction>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReportAuctionPrice<'info> {
+    pub auction: Account<'info, Auction>,
+}
+
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(mut,
+        seeds = [b"auction", minter.key().as_ref()],
+        bump = auction.bump,
+        constraint = auction.clearing_price.is_none() @ MinterError::InvalidAuction,
+    )]
+    pub auction: Account<'info, Auction>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimAuctionRebate<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+
+    #[account(mut)]
+    pub auction: Account<'info, Auction>,
+
+    #[account(mut,
+        seeds = [b"auction_receipt", auction.key().as_ref(), buyer.key.as_ref()],
+        bump = receipt.bump,
+        has_one = buyer @ MinterError::InvalidAuctionReceipt,
+        has_one = auction @ MinterError::InvalidAuctionReceipt,
+        close = buyer,
+    )]
+    pub receipt: Account<'info, AuctionReceipt>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawAuctionProceeds<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(mut,
+        seeds = [b"auction", minter.key().as_ref()],
+        bump = auction.bump,
+        constraint = auction.config.rebate @ MinterError::InvalidAuction,
+    )]
+    pub auction: Account<'info, Auction>,
+
+    /// CHECK: checked against the minter treasury
+    #[account(mut, address = minter.treasury @ MinterError::InvalidTreasury)]
+    pub treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct BindingCollection<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// CHECK: ['metadata', metadata::ID, mint, 'collection_authority', new_collection_authority]
+    #[account(mut)]
+    pub collection_authority_record: UncheckedAccount<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(mut,
+        constraint = &metadata.update_authority == authority.key,
+    )]
+    pub metadata: Account<'info, token_metadata::MetadataAccount>,
+
+    pub mint: Account<'info, token::Mint>,
+
+    #[account(init,
+        seeds = [COLLECTION_REGISTRY_KEY, minter.key().as_ref(), mint.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + CollectionRegistry::LEN,
+    )]
+    pub collection_registry: Account<'info, CollectionRegistry>,
+
+    pub token_program: Program<'info, token::Token>,
+    pub system_program: Program<'info, System>,
+    pub token_metadata_program: Program<'info, token_metadata::TokenMetadata>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> BindingCollection<'info> {
+    pub fn into_approve_collection_authority_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::ApproveCollectionAuthority<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::ApproveCollectionAuthority {
+            collection_authority_record: self.collection_authority_record.to_account_info(),
+            new_collection_authority: self.minter.to_account_info(),
+            update_authority: self.authority.to_account_info(),
+            payer: self.authority.to_account_info(),
+            metadata: self.metadata.to_account_info(),
+            mint: self.mint.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_set_collection_size_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::SetCollectionSize<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::SetCollectionSize {
+            metadata: self.metadata.to_account_info(),
+            update_authority: self.authority.to_account_info(),
+            mint: self.mint.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
+
+#[derive(Accounts)]
+pub struct CreateCollection<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    /// CHECK: ['collection_mint', minter, id.to_le_bytes()]
+    #[account(mut)]
+    pub mint: UncheckedAccount<'info>,
+
+    /// CHECK: [authority, token::ID, mint]
+    #[account(mut)]
+    pub associated_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: ['metadata', metadata::ID, mint]
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+
+    /// CHECK: ['metadata', metadata::ID, mint, 'edition']
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+
+    /// CHECK: ['metadata', metadata::ID, mint, 'collection_authority', minter]
+    #[account(mut)]
+    pub collection_authority_record: UncheckedAccount<'info>,
+
+    #[account(init,
+        seeds = [COLLECTION_REGISTRY_KEY, minter.key().as_ref(), mint.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + CollectionRegistry::LEN,
+    )]
+    pub collection_registry: Account<'info, CollectionRegistry>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_metadata_program: Program<'info, TokenMetadata>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> CreateCollection<'info> {
+    pub fn into_initialize_mint_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token::InitializeMint<'info>> {
+        let cpi_program = self.token_program.to_account_info();
+        let cpi_accounts = token::InitializeMint {
+            mint: self.mint.to_account_info(),
+            rent: self.rent.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_create_associated_token_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, associated_token::Create<'info>> {
+        let cpi_program = self.associated_token_program.to_account_info();
+        let cpi_accounts = associated_token::Create {
+            payer: self.authority.to_account_info(),
+            associated_token: self.associated_token_account.to_account_info(),
+            authority: self.authority.to_account_info(),
+            mint: self.mint.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_token_mint_to_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token::MintTo<'info>> {
+        let cpi_program = self.token_program.to_account_info();
+        let cpi_accounts = token::MintTo {
+            mint: self.mint.to_account_info(),
+            to: self.associated_token_account.to_account_info(),
+            authority: self.minter.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_create_metadata_context(
+        &self,
+        update_authority: &AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::CreateMetadataAccountV2<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::CreateMetadataAccountV2 {
+            metadata: self.metadata.to_account_info(),
+            mint: self.mint.to_account_info(),
+            mint_authority: self.minter.to_account_info(),
+            payer: self.authority.to_account_info(),
+            update_authority: update_authority.clone(),
+            system_program: self.system_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_create_master_edition_context(
+        &self,
+        update_authority: &AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::CreateMasterEditionV3<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::CreateMasterEditionV3 {
+            edition: self.master_edition.to_account_info(),
+            mint: self.mint.to_account_info(),
+            update_authority: update_authority.clone(),
+            mint_authority: self.minter.to_account_info(),
+            payer: self.authority.to_account_info(),
+            metadata: self.metadata.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_approve_collection_authority_context(
+        &self,
+        update_authority: &AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::ApproveCollectionAuthority<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::ApproveCollectionAuthority {
+            collection_authority_record: self.collection_authority_record.to_account_info(),
+            new_collection_authority: self.minter.to_account_info(),
+            update_authority: update_authority.clone(),
+            payer: self.authority.to_account_info(),
+            metadata: self.metadata.to_account_info(),
+            mint: self.mint.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_set_collection_size_context(
+        &self,
+        update_authority: &AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::SetCollectionSize<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::SetCollectionSize {
+            metadata: self.metadata.to_account_info(),
+            update_authority: update_authority.clone(),
+            mint: self.mint.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(id: u32)]
+pub struct CreateFungible<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(init,
+        seeds = [FUNGIBLE_KEY, minter.key().as_ref(), &id.to_le_bytes()],
+        bump,
+        payer = authority,
+        space = 8 + FungibleAsset::LEN,
+    )]
+    pub asset: Account<'info, FungibleAsset>,
+
+    /// CHECK: ['fungible_mint', minter, id.to_le_bytes()]
+    #[account(mut)]
+    pub mint: UncheckedAccount<'info>,
+
+    /// CHECK: [authority, token::ID, mint]
+    #[account(mut)]
+    pub associated_token_account: UncheckedAccount<'info>,
+
+    /// CHECK: ['metadata', metadata::ID, mint]
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_metadata_program: Program<'info, TokenMetadata>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> CreateFungible<'info> {
+    pub fn into_initialize_mint_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token::InitializeMint<'info>> {
+        let cpi_program = self.token_program.to_account_info();
+        let cpi_accounts = token::InitializeMint {
+            mint: self.mint.to_account_info(),
+            rent: self.rent.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_create_metadata_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::CreateMetadataAccountV2<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::CreateMetadataAccountV2 {
+            metadata: self.metadata.to_account_info(),
+            mint: self.mint.to_account_info(),
+            mint_authority: self.minter.to_account_info(),
+            payer: self.authority.to_account_info(),
+            update_authority: self.minter.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_create_associated_token_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, associated_token::Create<'info>> {
+        let cpi_program = self.associated_token_program.to_account_info();
+        let cpi_accounts = associated_token::Create {
+            payer: self.authority.to_account_info(),
+            associated_token: self.associated_token_account.to_account_info(),
+            authority: self.authority.to_account_info(),
+            mint: self.mint.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_token_mint_to_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token::MintTo<'info>> {
+        let cpi_program = self.token_program.to_account_info();
+        let cpi_accounts = token::MintTo {
+            mint: self.mint.to_account_info(),
+            to: self.associated_token_account.to_account_info(),
+            authority: self.minter.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
+
+#[derive(Accounts)]
+pub struct MintFungible<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(
+        seeds = [FUNGIBLE_KEY, minter.key().as_ref(), &asset.id.to_le_bytes()],
+        bump = asset.bump,
+        has_one = mint,
+    )]
+    pub asset: Account<'info, FungibleAsset>,
+
+    #[account(mut)]
+    pub mint: Account<'info, Mint>,
+
+    #[account(mut, token::mint = mint)]
+    pub destination: Account<'info, TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+impl<'info> MintFungible<'info> {
+    pub fn into_token_mint_to_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token::MintTo<'info>> {
+        let cpi_program = self.token_program.to_account_info();
+        let cpi_accounts = token::MintTo {
+            mint: self.mint.to_account_info(),
+            to: self.destination.to_account_info(),
+            authority: self.minter.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
+
+#[derive(Accounts)]
+pub struct RegisterCollection<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+        constraint = minter.collection == Some(collection_mint.key()) @ MinterError::MismatchedCollection,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    pub collection_mint: Account<'info, token::Mint>,
+
+    #[account(init,
+        seeds = [COLLECTION_REGISTRY_KEY, minter.key().as_ref(), collection_mint.key().as_ref()],
+        bump,
+        payer = authority,
+        space = 8 + CollectionRegistry::LEN,
+    )]
+    pub collection_registry: Account<'info, CollectionRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CollectionItem<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    #[account(mut,
+        constraint = metadata.update_authority == minter.key() @ MinterError::NotIssuedByMinter,
+    )]
+    pub metadata: Box<Account<'info, token_metadata::MetadataAccount>>,
+
+    #[account(
+        seeds = [b"metadata", token_metadata_program.key.as_ref(), collection_mint.key().as_ref(), b"collection_authority", minter.key().as_ref()],
+        bump,
+        seeds::program = token_metadata_program.key,
+    )]
+    pub collection_authority_record: Box<Account<'info, token_metadata::CollectionAuthorityRecord>>,
+
+    pub collection_mint: Box<Account<'info, token::Mint>>,
+    #[account(mut)]
+    pub collection: Box<Account<'info, token_metadata::MetadataAccount>>,
+    pub collection_master_edition_account:
+        Box<Account<'info, token_metadata::MasterEditionAccount>>,
+
+    pub token_metadata_program: Program<'info, TokenMetadata>,
+}
+
+impl<'info> CollectionItem<'info> {
+    pub fn into_unverify_collection_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::UnverifyCollection<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::UnverifyCollection {
+            metadata: self.metadata.to_account_info(),
+            collection_authority: self.minter.to_account_info(),
+            collection_mint: self.collection_mint.to_account_info(),
+            collection: self.collection.to_account_info(),
+            collection_master_edition_account: self
+                .collection_master_edition_account
+                .to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_unverify_sized_collection_item_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::UnverifySizedCollectionItem<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::UnverifySizedCollectionItem {
+            metadata: self.metadata.to_account_info(),
+            collection_authority: self.minter.to_account_info(),
+            payer: self.authority.to_account_info(),
+            collection_mint: self.collection_mint.to_account_info(),
+            collection: self.collection.to_account_info(),
+            collection_master_edition_account: self
+                .collection_master_edition_account
+                .to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_set_and_verify_collection_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::SetAndVerifyCollection<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::SetAndVerifyCollection {
+            metadata: self.metadata.to_account_info(),
+            collection_authority: self.minter.to_account_info(),
+            payer: self.authority.to_account_info(),
+            update_authority: self.minter.to_account_info(),
+            collection_mint: self.collection_mint.to_account_info(),
+            collection: self.collection.to_account_info(),
+            collection_master_edition_account: self
+                .collection_master_edition_account
+                .to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
 
-        token_metadata::verify_collection(
-            ctx.accounts
-                .into_verify_collection_context()
-                .with_remaining_accounts(vec![ctx
-                    .accounts
-                    .collection_authority_record
-                    .to_account_info()
-                    .clone()])
-                .with_signer(&[&minter_seeds[..]]),
-        )?;
+    pub fn into_update_metadata_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::UpdateMetadataAccountsV2<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::UpdateMetadataAccountsV2 {
+            metadata: self.metadata.to_account_info(),
+            update_authority: self.minter.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
 
-        ctx.accounts.minter.reload()?;
-        let minter = &mut ctx.accounts.minter;
-        minter.count += 1;
-        Ok(())
+    pub fn into_verify_collection_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::VerifyCollection<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::VerifyCollection {
+            metadata: self.metadata.to_account_info(),
+            collection_authority: self.minter.to_account_info(),
+            payer: self.authority.to_account_info(),
+            collection_mint: self.collection_mint.to_account_info(),
+            collection: self.collection.to_account_info(),
+            collection_master_edition_account: self
+                .collection_master_edition_account
+                .to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_set_and_verify_sized_collection_item_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::SetAndVerifySizedCollectionItem<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::SetAndVerifySizedCollectionItem {
+            metadata: self.metadata.to_account_info(),
+            collection_authority: self.minter.to_account_info(),
+            payer: self.authority.to_account_info(),
+            update_authority: self.minter.to_account_info(),
+            collection_mint: self.collection_mint.to_account_info(),
+            collection: self.collection.to_account_info(),
+            collection_master_edition_account: self
+                .collection_master_edition_account
+                .to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
     }
 }
 
 #[derive(Accounts)]
-pub struct InitializeMinter<'info> {
+pub struct AddV1ItemsToCollection<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
 
-    #[account(init,
-        seeds = [b"minter", authority.key.as_ref()],
-        bump,
-        payer = authority,
-        space = 8 + 32 + 4,
+    #[account(
+        seeds = [b"minter", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
+        constraint = minter.version == MINTER_LAYOUT_VERSION @ MinterError::OutdatedMinterLayout,
     )]
     pub minter: Account<'info, MinterAccount>,
 
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
-}
-
-#[derive(Accounts)]
-pub struct InitializeMinterV2<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"minter_v2", minter_v2.owner.as_ref()],
+        bump = minter_v2.bump,
+        constraint = &minter_v2.owner == authority.key @ MinterError::MismatchedMinterOwner,
+        constraint = minter_v2.collection == Some(collection_mint.key()) @ MinterError::MismatchedCollection,
+    )]
+    pub minter_v2: Account<'info, CurrentMinterV2>,
 
-    #[account(init,
-        seeds = [b"minter_v2", authority.key.as_ref()],
+    #[account(
+        seeds = [b"metadata", token_metadata_program.key.as_ref(), collection_mint.key().as_ref(), b"collection_authority", minter_v2.key().as_ref()],
         bump,
-        payer = authority,
-        space = 8 + 32 + 4 + 33,
+        seeds::program = token_metadata_program.key,
     )]
-    pub minter: Account<'info, MinterAccountV2>,
-
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
-}
-
-#[account]
-#[derive(Default, Debug)]
-#[repr(C)]
-pub struct MinterAccountV2 {
-    pub owner: Pubkey,
-    pub count: u32,
-    pub collection: Option<Pubkey>,
-}
-
-#[account]
-#[derive(Default, Debug)]
-#[repr(C)]
-pub struct MinterAccount {
-    pub owner: Pubkey,
-    pub count: u32,
-}
+    pub collection_authority_record: Box<Account<'info, token_metadata::CollectionAuthorityRecord>>,
 
-#[derive(Accounts)]
-pub struct BindingCollection<'info> {
+    pub collection_mint: Box<Account<'info, token::Mint>>,
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub collection: Box<Account<'info, token_metadata::MetadataAccount>>,
+    pub collection_master_edition_account:
+        Box<Account<'info, token_metadata::MasterEditionAccount>>,
 
-    /// CHECK: ['metadata', metadata::ID, mint, 'collection_authority', new_collection_authority]
-    #[account(mut)]
-    pub collection_authority_record: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, TokenMetadata>,
+}
 
-    #[account(mut,
-        constraint = &minter.owner == authority.key @ MinterError::MismatchedMinterOwner,
-    )]
-    pub minter: Account<'info, MinterAccountV2>,
+impl<'info> AddV1ItemsToCollection<'info> {
+    pub fn into_update_metadata_context(
+        &self,
+        metadata: &AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::UpdateMetadataAccountsV2<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::UpdateMetadataAccountsV2 {
+            metadata: metadata.clone(),
+            update_authority: self.minter.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
 
-    #[account(
-        constraint = &metadata.update_authority == authority.key,
-    )]
-    pub metadata: Account<'info, token_metadata::MetadataAccount>,
+    pub fn into_verify_collection_context(
+        &self,
+        metadata: &AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::VerifyCollection<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::VerifyCollection {
+            metadata: metadata.clone(),
+            collection_authority: self.minter_v2.to_account_info(),
+            payer: self.authority.to_account_info(),
+            collection_mint: self.collection_mint.to_account_info(),
+            collection: self.collection.to_account_info(),
+            collection_master_edition_account: self
+                .collection_master_edition_account
+                .to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
 
-    pub mint: Account<'info, token::Mint>,
-    pub token_program: Program<'info, token::Token>,
-    pub system_program: Program<'info, System>,
-    pub token_metadata_program: Program<'info, token_metadata::TokenMetadata>,
-    pub rent: Sysvar<'info, Rent>,
-}
+    pub fn into_set_and_verify_collection_context(
+        &self,
+        metadata: &AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::SetAndVerifyCollection<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::SetAndVerifyCollection {
+            metadata: metadata.clone(),
+            collection_authority: self.minter_v2.to_account_info(),
+            payer: self.authority.to_account_info(),
+            update_authority: self.minter.to_account_info(),
+            collection_mint: self.collection_mint.to_account_info(),
+            collection: self.collection.to_account_info(),
+            collection_master_edition_account: self
+                .collection_master_edition_account
+                .to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
 
-impl<'info> BindingCollection<'info> {
-    pub fn into_approve_collection_authority_context(
+    pub fn into_set_and_verify_sized_collection_item_context(
         &self,
-    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::ApproveCollectionAuthority<'info>> {
+        metadata: &AccountInfo<'info>,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::SetAndVerifySizedCollectionItem<'info>> {
         let cpi_program = self.token_metadata_program.to_account_info();
-        let cpi_accounts = token_metadata::ApproveCollectionAuthority {
-            collection_authority_record: self.collection_authority_record.to_account_info(),
-            new_collection_authority: self.minter.to_account_info(),
-            update_authority: self.authority.to_account_info(),
+        let cpi_accounts = token_metadata::SetAndVerifySizedCollectionItem {
+            metadata: metadata.clone(),
+            collection_authority: self.minter_v2.to_account_info(),
             payer: self.authority.to_account_info(),
-            metadata: self.metadata.to_account_info(),
-            mint: self.mint.to_account_info(),
-            token_program: self.token_program.to_account_info(),
-            system_program: self.system_program.to_account_info(),
-            rent: self.rent.to_account_info(),
+            update_authority: self.minter.to_account_info(),
+            collection_mint: self.collection_mint.to_account_info(),
+            collection: self.collection.to_account_info(),
+            collection_master_edition_account: self
+                .collection_master_edition_account
+                .to_account_info(),
         };
         CpiContext::new(cpi_program, cpi_accounts)
     }
@@ -379,11 +3967,15 @@ impl<'info> BindingCollection<'info> {
 
 #[derive(Accounts)]
 pub struct BasicMint<'info> {
-    /// CHECK: ['mint', count.to_le_bytes()] or ['mint_v2', count.to_le_bytes()]
+    /// CHECK: ['mint', count.to_le_bytes()] or ['mint_v2', count.to_le_bytes()],
+    /// a signing keypair for unordered mints
     #[account(mut)]
     pub mint: UncheckedAccount<'info>,
 
-    /// CHECK: [payer, token::ID, mint]
+    /// CHECK: any account, receives the NFT
+    pub recipient: UncheckedAccount<'info>,
+
+    /// CHECK: [recipient, token::ID, mint]
     #[account(mut)]
     pub associated_token_account: UncheckedAccount<'info>,
 
@@ -422,7 +4014,7 @@ impl<'info> BasicMint<'info> {
         let cpi_accounts = associated_token::Create {
             payer: payer.to_account_info(),
             associated_token: self.associated_token_account.to_account_info(),
-            authority: payer.to_account_info(),
+            authority: self.recipient.to_account_info(),
             mint: self.mint.to_account_info(),
             system_program: self.system_program.to_account_info(),
             token_program: self.token_program.to_account_info(),
@@ -500,13 +4092,105 @@ pub struct MintFor<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    #[account(mut)]
+    #[account(mut,
+        seeds = [b"minter", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = minter.version == MINTER_LAYOUT_VERSION @ MinterError::OutdatedMinterLayout,
+    )]
     pub minter: Account<'info, MinterAccount>,
 
+    /// CHECK: ['mint_record', mint], created by the instruction
+    #[account(mut)]
+    pub mint_record: UncheckedAccount<'info>,
+
+    pub basic: BasicMint<'info>,
+}
+
+impl<'info> Deref for MintFor<'info> {
+    type Target = BasicMint<'info>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.basic
+    }
+}
+
+impl<'info> DerefMut for MintFor<'info> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.basic
+    }
+}
+
+#[derive(Accounts)]
+pub struct MintWithCollection<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut,
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = minter.token_2022.is_none() @ MinterError::Token2022Enabled,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    /// CHECK: ['mint_record', mint], created by the instruction
+    #[account(mut)]
+    pub mint_record: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [COLLECTION_REGISTRY_KEY, minter.key().as_ref(), collection_mint.key().as_ref()],
+        bump = collection_registry.bump,
+    )]
+    pub collection_registry: Account<'info, CollectionRegistry>,
+
+    #[account(
+        seeds = [b"metadata", basic.token_metadata_program.key.as_ref(), collection_mint.key().as_ref(), b"collection_authority", minter.key().as_ref()],
+        bump,
+        seeds::program = basic.token_metadata_program.key,
+    )]
+    pub collection_authority_record: Box<Account<'info, token_metadata::CollectionAuthorityRecord>>,
+
+    pub collection_mint: Box<Account<'info, token::Mint>>,
+    #[account(mut)]
+    pub collection: Box<Account<'info, token_metadata::MetadataAccount>>,
+    pub collection_master_edition_account:
+        Box<Account<'info, token_metadata::MasterEditionAccount>>,
+
     pub basic: BasicMint<'info>,
 }
 
-impl<'info> Deref for MintFor<'info> {
+impl<'info> MintWithCollection<'info> {
+    pub fn into_verify_collection_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::VerifyCollection<'info>> {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::VerifyCollection {
+            metadata: self.metadata.to_account_info(),
+            collection_authority: self.minter.to_account_info(),
+            payer: self.payer.to_account_info(),
+            collection_mint: self.collection_mint.to_account_info(),
+            collection: self.collection.to_account_info(),
+            collection_master_edition_account: self
+                .collection_master_edition_account
+                .to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+
+    pub fn into_update_primary_sale_happened_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::UpdatePrimarySaleHappenedViaToken<'info>>
+    {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::UpdatePrimarySaleHappenedViaToken {
+            metadata: self.metadata.to_account_info(),
+            owner: self.recipient.to_account_info(),
+            token: self.associated_token_account.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
+
+impl<'info> Deref for MintWithCollection<'info> {
     type Target = BasicMint<'info>;
 
     fn deref(&self) -> &Self::Target {
@@ -514,19 +4198,37 @@ impl<'info> Deref for MintFor<'info> {
     }
 }
 
-impl<'info> DerefMut for MintFor<'info> {
+impl<'info> DerefMut for MintWithCollection<'info> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.basic
     }
 }
 
 #[derive(Accounts)]
-pub struct MintWithCollection<'info> {
+pub struct MintUnordered<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
 
+    #[account(
+        seeds = [b"minter_v2", minter.owner.as_ref()],
+        bump = minter.bump,
+        constraint = minter.token_2022.is_none() @ MinterError::Token2022Enabled,
+    )]
+    pub minter: Account<'info, CurrentMinterV2>,
+
+    /// CHECK: ['mint_record', mint], created by the instruction
     #[account(mut)]
-    pub minter: Account<'info, MinterAccountV2>,
+    pub mint_record: UncheckedAccount<'info>,
+
+    /// CHECK: ['mint_counter', minter, shard], created on first use
+    #[account(mut)]
+    pub counter: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [COLLECTION_REGISTRY_KEY, minter.key().as_ref(), collection_mint.key().as_ref()],
+        bump = collection_registry.bump,
+    )]
+    pub collection_registry: Account<'info, CollectionRegistry>,
 
     #[account(
         seeds = [b"metadata", basic.token_metadata_program.key.as_ref(), collection_mint.key().as_ref(), b"collection_authority", minter.key().as_ref()],
@@ -543,7 +4245,7 @@ pub struct MintWithCollection<'info> {
     pub basic: BasicMint<'info>,
 }
 
-impl<'info> MintWithCollection<'info> {
+impl<'info> MintUnordered<'info> {
     pub fn into_verify_collection_context(
         &self,
     ) -> CpiContext<'_, '_, '_, 'info, token_metadata::VerifyCollection<'info>> {
@@ -560,9 +4262,22 @@ impl<'info> MintWithCollection<'info> {
         };
         CpiContext::new(cpi_program, cpi_accounts)
     }
+
+    pub fn into_update_primary_sale_happened_context(
+        &self,
+    ) -> CpiContext<'_, '_, '_, 'info, token_metadata::UpdatePrimarySaleHappenedViaToken<'info>>
+    {
+        let cpi_program = self.token_metadata_program.to_account_info();
+        let cpi_accounts = token_metadata::UpdatePrimarySaleHappenedViaToken {
+            metadata: self.metadata.to_account_info(),
+            owner: self.recipient.to_account_info(),
+            token: self.associated_token_account.to_account_info(),
+        };
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
 }
 
-impl<'info> Deref for MintWithCollection<'info> {
+impl<'info> Deref for MintUnordered<'info> {
     type Target = BasicMint<'info>;
 
     fn deref(&self) -> &Self::Target {
@@ -570,7 +4285,7 @@ impl<'info> Deref for MintWithCollection<'info> {
     }
 }
 
-impl<'info> DerefMut for MintWithCollection<'info> {
+impl<'info> DerefMut for MintUnordered<'info> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.basic
     }
@@ -605,6 +4320,931 @@ pub fn allocate_mint_with_collection(
     )
 }
 
+/// Counts a mint in the ['mint_counter', minter, shard] account, created on
+/// first use, and returns the count it was made at.
+pub fn increment_mint_counter<'info>(
+    minter: &Pubkey,
+    shard: u8,
+    counter_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    rent: &AccountInfo<'info>,
+) -> Result<u64> {
+    if counter_info.data_is_empty() {
+        let (counter_addr, bump) = Pubkey::find_program_address(
+            &[MINT_COUNTER_KEY, minter.as_ref(), &[shard]],
+            &crate::ID,
+        );
+        if counter_info.key != &counter_addr {
+            return Err(MinterError::InvalidMintCounter.into());
+        }
+        create_or_allocate_account_raw(
+            crate::ID,
+            counter_info,
+            rent,
+            system_program,
+            payer,
+            8 + MintCounter::LEN,
+            &[&[MINT_COUNTER_KEY, minter.as_ref(), &[shard], &[bump]]],
+        )?;
+
+        let mut counter = Account::<MintCounter>::try_from_unchecked(counter_info)?;
+        counter.minter = *minter;
+        counter.shard = shard;
+        counter.bump = bump;
+        counter.exit(&crate::ID)?;
+    }
+
+    let mut counter = Account::<MintCounter>::try_from(counter_info)?;
+    if &counter.minter != minter || counter.shard != shard {
+        return Err(MinterError::InvalidMintCounter.into());
+    }
+    let index = counter.count;
+    counter.count = index.checked_add(1).ok_or(MinterError::CounterOverflow)?;
+    counter.exit(&crate::ID)?;
+    Ok(index)
+}
+
+/// Loads the record of `mint` and checks that it was issued by `minter`.
+pub fn verify_provenance(
+    record_info: &AccountInfo,
+    mint: &Pubkey,
+    minter: &Pubkey,
+) -> Result<MintRecord> {
+    let record = Account::<MintRecord>::try_from(record_info)?;
+    if &record.mint != mint || &record.minter != minter {
+        return Err(MinterError::ProvenanceMismatch.into());
+    }
+    Ok(record.into_inner())
+}
+
+/// Creates the record of a new mint at ['mint_record', mint, bump].
+pub fn create_mint_record<'info>(
+    record_info: &AccountInfo<'info>,
+    payer_info: &AccountInfo<'info>,
+    system_program_info: &AccountInfo<'info>,
+    rent_sysvar_info: &AccountInfo<'info>,
+    record: MintRecord,
+) -> Result<()> {
+    let record_seeds = &[MINT_RECORD_KEY, record.mint.as_ref(), &[record.bump]];
+    let record_addr = Pubkey::create_program_address(&record_seeds[..], &crate::ID)
+        .map_err(|_| MinterError::InvalidMintRecordAddress)?;
+    if record_info.key != &record_addr {
+        return Err(MinterError::InvalidMintRecordAddress.into());
+    }
+
+    create_or_allocate_account_raw(
+        crate::ID,
+        record_info,
+        rent_sysvar_info,
+        system_program_info,
+        payer_info,
+        8 + MintRecord::LEN,
+        &[&record_seeds[..]],
+    )?;
+
+    let mut data = record_info.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    record.try_serialize(&mut writer)
+}
+
+/// Seed of the mint at `index`. Indices that fit in a u32 keep the 4 byte
+/// seed used before the counter was widened, so existing mints stay derivable.
+pub fn mint_index_seed(index: u64) -> Vec<u8> {
+    match u32::try_from(index) {
+        Ok(index) => index.to_le_bytes().to_vec(),
+        Err(_) => index.to_le_bytes().to_vec(),
+    }
+}
+
+/// True when `creator` signed the metadata, which only this program can do
+/// for a minter PDA.
+pub fn is_verified_creator(metadata: &token_metadata::MetadataAccount, creator: &Pubkey) -> bool {
+    metadata
+        .data
+        .creators
+        .iter()
+        .flatten()
+        .any(|c| &c.address == creator && c.verified)
+}
+
+/// Current data of an NFT, in the shape update_metadata_accounts_v2 expects.
+pub fn data_v2(metadata: &token_metadata::MetadataAccount) -> token_metadata::state::DataV2 {
+    token_metadata::state::DataV2 {
+        name: metadata.data.name.trim_matches(char::from(0)).to_string(),
+        symbol: metadata.data.symbol.trim_matches(char::from(0)).to_string(),
+        uri: metadata.data.uri.trim_matches(char::from(0)).to_string(),
+        seller_fee_basis_points: metadata.data.seller_fee_basis_points,
+        creators: metadata.data.creators.clone(),
+        collection: metadata.collection.clone(),
+        uses: metadata.uses.clone(),
+    }
+}
+
+/// Checks the metadata against the Token Metadata limits before any CPI.
+pub fn validate_metadata(
+    name: &str,
+    symbol: &str,
+    uri: &str,
+    seller_fee_basis_points: u16,
+) -> Result<()> {
+    if name.len() > token_metadata::state::MAX_NAME_LENGTH {
+        return Err(MinterError::NameTooLong.into());
+    }
+    if symbol.len() > token_metadata::state::MAX_SYMBOL_LENGTH {
+        return Err(MinterError::SymbolTooLong.into());
+    }
+    if uri.len() > token_metadata::state::MAX_URI_LENGTH {
+        return Err(MinterError::UriTooLong.into());
+    }
+    if seller_fee_basis_points > MAX_BASIS_POINTS {
+        return Err(MinterError::InvalidSellerFeeBasisPoints.into());
+    }
+    Ok(())
+}
+
+/// Checks that the payer holds the gate token. Takes from `remaining_accounts`:
+/// - the payer's gate token account
+/// - `Collection` gates: the gate NFT metadata
+/// - `Collection` gates with `max_uses`: the gate usage PDA
+///   `['gate_usage', minter, gate mint]` (mut), created on first use
+pub fn check_token_gate<'a, 'info: 'a>(
+    gate: &TokenGate,
+    minter: &Pubkey,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    rent: &AccountInfo<'info>,
+    remaining_accounts: &mut std::slice::Iter<'a, AccountInfo<'info>>,
+) -> Result<()> {
+    let gate_token = Account::<TokenAccount>::try_from(next_account_info(remaining_accounts)?)?;
+    if &gate_token.owner != payer.key {
+        return Err(MinterError::InvalidGateToken.into());
+    }
+
+    let (collection, max_uses) = match gate {
+        TokenGate::Mint { mint, min_amount } => {
+            if &gate_token.mint != mint || gate_token.amount < *min_amount {
+                return Err(MinterError::InvalidGateToken.into());
+            }
+            return Ok(());
+        }
+        TokenGate::Collection {
+            collection,
+            max_uses,
+        } => (collection, max_uses),
+    };
+
+    let gate_metadata = Account::<token_metadata::MetadataAccount>::try_from(next_account_info(
+        remaining_accounts,
+    )?)?;
+    if gate_token.amount == 0 || gate_metadata.mint != gate_token.mint {
+        return Err(MinterError::InvalidGateToken.into());
+    }
+    match &gate_metadata.collection {
+        Some(c) if &c.key == collection && c.verified => {}
+        _ => return Err(MinterError::GateCollectionMismatch.into()),
+    }
+
+    let max_uses = match max_uses {
+        Some(max_uses) => *max_uses,
+        None => return Ok(()),
+    };
+    let usage_info = next_account_info(remaining_accounts)?;
+    if usage_info.data_is_empty() {
+        let (usage_addr, bump) = Pubkey::find_program_address(
+            &[GATE_USAGE_KEY, minter.as_ref(), gate_token.mint.as_ref()],
+            &crate::ID,
+        );
+        if usage_info.key != &usage_addr {
+            return Err(MinterError::InvalidGateUsage.into());
+        }
+        create_or_allocate_account_raw(
+            crate::ID,
+            usage_info,
+            rent,
+            system_program,
+            payer,
+            8 + GateUsage::LEN,
+            &[&[
+                GATE_USAGE_KEY,
+                minter.as_ref(),
+                gate_token.mint.as_ref(),
+                &[bump],
+            ]],
+        )?;
+
+        let mut usage = Account::<GateUsage>::try_from_unchecked(usage_info)?;
+        usage.minter = *minter;
+        usage.gate_mint = gate_token.mint;
+        usage.bump = bump;
+        usage.exit(&crate::ID)?;
+    }
+
+    let mut usage = Account::<GateUsage>::try_from(usage_info)?;
+    if &usage.minter != minter || usage.gate_mint != gate_token.mint {
+        return Err(MinterError::InvalidGateUsage.into());
+    }
+    if usage.uses >= max_uses {
+        return Err(MinterError::GateUsageExceeded.into());
+    }
+    usage.uses += 1;
+    usage.exit(&crate::ID)
+}
+
+/// Loads the phase the mint goes through and counts the mint against the
+/// payer's wallet limit. Takes from `remaining_accounts` the phase, then, if
+/// it has a wallet limit, the payer's ['wallet_mints', phase, payer] (mut).
+pub fn enter_phase<'a, 'info: 'a>(
+    minter: &Pubkey,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    rent: &AccountInfo<'info>,
+    remaining_accounts: &mut std::slice::Iter<'a, AccountInfo<'info>>,
+) -> Result<PhaseConfig> {
+    let phase_info = next_account_info(remaining_accounts)?;
+    let phase = Account::<Phase>::try_from(phase_info)?;
+    let phase_addr = Pubkey::create_program_address(
+        &[PHASE_KEY, minter.as_ref(), &[phase.id], &[phase.bump]],
+        &crate::ID,
+    )
+    .map_err(|_| MinterError::InvalidPhase)?;
+    if &phase.minter != minter || phase_info.key != &phase_addr {
+        return Err(MinterError::InvalidPhase.into());
+    }
+    if !phase.config.is_active(Clock::get()?.unix_timestamp) {
+        return Err(MinterError::PhaseNotActive.into());
+    }
+
+    let wallet_limit = match phase.config.wallet_limit {
+        Some(wallet_limit) => wallet_limit,
+        None => return Ok(phase.config.clone()),
+    };
+    let mints_info = next_account_info(remaining_accounts)?;
+    if mints_info.data_is_empty() {
+        let (mints_addr, bump) = Pubkey::find_program_address(
+            &[WALLET_MINTS_KEY, phase_addr.as_ref(), payer.key.as_ref()],
+            &crate::ID,
+        );
+        if mints_info.key != &mints_addr {
+            return Err(MinterError::InvalidWalletMints.into());
+        }
+        create_or_allocate_account_raw(
+            crate::ID,
+            mints_info,
+            rent,
+            system_program,
+            payer,
+            8 + WalletMints::LEN,
+            &[&[
+                WALLET_MINTS_KEY,
+                phase_addr.as_ref(),
+                payer.key.as_ref(),
+                &[bump],
+            ]],
+        )?;
+
+        let mut mints = Account::<WalletMints>::try_from_unchecked(mints_info)?;
+        mints.phase = phase_addr;
+        mints.wallet = *payer.key;
+        mints.bump = bump;
+        mints.exit(&crate::ID)?;
+    }
+
+    let mut mints = Account::<WalletMints>::try_from(mints_info)?;
+    if mints.phase != phase_addr || &mints.wallet != payer.key {
+        return Err(MinterError::InvalidWalletMints.into());
+    }
+    if mints.count >= wallet_limit {
+        return Err(MinterError::WalletLimitExceeded.into());
+    }
+    mints.count += 1;
+    mints.exit(&crate::ID)?;
+    Ok(phase.config.clone())
+}
+
+/// Checks `config` against the other phases of the minter. All of them have
+/// to be passed, in increasing id order, so that none can be left out.
+pub fn check_phase_overlap<'info>(
+    minter: &Pubkey,
+    config: &PhaseConfig,
+    edited: Option<u8>,
+    others: u8,
+    phase_infos: &[AccountInfo<'info>],
+) -> Result<()> {
+    if phase_infos.len() != usize::from(others) {
+        return Err(MinterError::MissingPhases.into());
+    }
+    let mut last_id = None;
+    for phase_info in phase_infos {
+        let phase = Account::<Phase>::try_from(phase_info)?;
+        if &phase.minter != minter {
+            return Err(MinterError::InvalidPhase.into());
+        }
+        if Some(phase.id) == edited || matches!(last_id, Some(id) if phase.id <= id) {
+            return Err(MinterError::MissingPhases.into());
+        }
+        last_id = Some(phase.id);
+        if phase.config.overlaps(config) {
+            return Err(MinterError::OverlappingPhases.into());
+        }
+    }
+    Ok(())
+}
+
+/// Records a mint at the current price of the minter's auction and returns
+/// the price, and whether it was paid into the auction. Takes from
+/// `remaining_accounts` the auction (mut), then in rebate mode the payer's
+/// receipt ['auction_receipt', auction, payer] (mut). In rebate mode the
+/// auction holds the proceeds, otherwise the caller pays the treasury.
+pub fn charge_auction<'a, 'info: 'a>(
+    minter: &Account<'info, CurrentMinterV2>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    rent: &AccountInfo<'info>,
+    remaining_accounts: &mut std::slice::Iter<'a, AccountInfo<'info>>,
+) -> Result<(u64, bool)> {
+    let auction_info = next_account_info(remaining_accounts)?;
+    let (auction_addr, _) =
+        Pubkey::find_program_address(&[AUCTION_KEY, minter.key().as_ref()], &crate::ID);
+    if auction_info.key != &auction_addr {
+        return Err(MinterError::InvalidAuction.into());
+    }
+    let mut auction = Account::<Auction>::try_from(auction_info)?;
+
+    let timestamp = Clock::get()?.unix_timestamp;
+    if timestamp < auction.config.start_time {
+        return Err(MinterError::AuctionNotStarted.into());
+    }
+    let price = auction.config.price_at(timestamp);
+
+    if auction.config.rebate {
+        invoke(
+            &system_instruction::transfer(payer.key, auction_info.key, price),
+            &[payer.clone(), auction_info.clone(), system_program.clone()],
+        )?;
+
+        let receipt_info = next_account_info(remaining_accounts)?;
+        if receipt_info.data_is_empty() {
+            let (receipt_addr, bump) = Pubkey::find_program_address(
+                &[
+                    AUCTION_RECEIPT_KEY,
+                    auction_addr.as_ref(),
+                    payer.key.as_ref(),
+                ],
+                &crate::ID,
+            );
+            if receipt_info.key != &receipt_addr {
+                return Err(MinterError::InvalidAuctionReceipt.into());
+            }
+            create_or_allocate_account_raw(
+                crate::ID,
+                receipt_info,
+                rent,
+                system_program,
+                payer,
+                8 + AuctionReceipt::LEN,
+                &[&[
+                    AUCTION_RECEIPT_KEY,
+                    auction_addr.as_ref(),
+                    payer.key.as_ref(),
+                    &[bump],
+                ]],
+            )?;
+
+            let mut receipt = Account::<AuctionReceipt>::try_from_unchecked(receipt_info)?;
+            receipt.auction = auction_addr;
+            receipt.buyer = *payer.key;
+            receipt.bump = bump;
+            receipt.exit(&crate::ID)?;
+        }
+
+        let mut receipt = Account::<AuctionReceipt>::try_from(receipt_info)?;
+        if receipt.auction != auction_addr || &receipt.buyer != payer.key {
+            return Err(MinterError::InvalidAuctionReceipt.into());
+        }
+        receipt.count = receipt
+            .count
+            .checked_add(1)
+            .ok_or(MinterError::CounterOverflow)?;
+        receipt.total_paid = receipt
+            .total_paid
+            .checked_add(price)
+            .ok_or(MinterError::PaymentOverflow)?;
+        receipt.exit(&crate::ID)?;
+    }
+
+    auction.sold = auction
+        .sold
+        .checked_add(1)
+        .ok_or(MinterError::CounterOverflow)?;
+    auction.total_paid = auction
+        .total_paid
+        .checked_add(price)
+        .ok_or(MinterError::PaymentOverflow)?;
+    auction.last_price = price;
+    auction.exit(&crate::ID)?;
+    Ok((price, auction.config.rebate))
+}
+
+/// Pays `price` into the minter's vault and records it in a
+/// ['payment_receipt', mint] account. Takes from `remaining_accounts` the
+/// vault and the receipt, both mut.
+pub fn escrow_payment<'a, 'info: 'a>(
+    escrow: &EscrowConfig,
+    minter: &Pubkey,
+    mint: &Pubkey,
+    price: u64,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    rent: &AccountInfo<'info>,
+    remaining_accounts: &mut std::slice::Iter<'a, AccountInfo<'info>>,
+) -> Result<()> {
+    let vault_info = next_account_info(remaining_accounts)?;
+    let mut vault = Account::<Vault>::try_from(vault_info)?;
+    let vault_addr =
+        Pubkey::create_program_address(&[VAULT_KEY, minter.as_ref(), &[vault.bump]], &crate::ID)
+            .map_err(|_| MinterError::InvalidVault)?;
+    if vault_info.key != &vault_addr || &vault.minter != minter {
+        return Err(MinterError::InvalidVault.into());
+    }
+    // a drop that missed its threshold stays closed
+    if Clock::get()?.unix_timestamp >= escrow.deadline && vault.sold < escrow.threshold {
+        return Err(MinterError::DropFailed.into());
+    }
+
+    if price > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, vault_info.key, price),
+            &[payer.clone(), vault_info.clone(), system_program.clone()],
+        )?;
+    }
+
+    let receipt_info = next_account_info(remaining_accounts)?;
+    let (receipt_addr, bump) =
+        Pubkey::find_program_address(&[PAYMENT_RECEIPT_KEY, mint.as_ref()], &crate::ID);
+    if receipt_info.key != &receipt_addr {
+        return Err(MinterError::InvalidPaymentReceipt.into());
+    }
+    create_or_allocate_account_raw(
+        crate::ID,
+        receipt_info,
+        rent,
+        system_program,
+        payer,
+        8 + PaymentReceipt::LEN,
+        &[&[PAYMENT_RECEIPT_KEY, mint.as_ref(), &[bump]]],
+    )?;
+    let mut receipt = Account::<PaymentReceipt>::try_from_unchecked(receipt_info)?;
+    receipt.set_inner(PaymentReceipt {
+        mint: *mint,
+        minter: *minter,
+        payer: *payer.key,
+        amount: price,
+        deadline: escrow.deadline,
+        bump,
+    });
+    receipt.exit(&crate::ID)?;
+
+    vault.sold = vault
+        .sold
+        .checked_add(1)
+        .ok_or(MinterError::CounterOverflow)?;
+    vault.exit(&crate::ID)
+}
+
+/// Shares of `amount` for each of `payouts`, the last one getting the
+/// rounding remainder.
+pub fn split_proceeds(amount: u64, payouts: &[Payout]) -> Vec<u64> {
+    let mut remaining = amount;
+    payouts
+        .iter()
+        .enumerate()
+        .map(|(i, payout)| {
+            let share = if i == payouts.len() - 1 {
+                remaining
+            } else {
+                (amount as u128 * payout.basis_points as u128 / MAX_BASIS_POINTS as u128) as u64
+            };
+            remaining -= share;
+            share
+        })
+        .collect()
+}
+
+/// Splits `amount` across the minter's payout recipients, see
+/// [`split_proceeds`]. `accounts` holds, all mut, the payer's
+/// token account if the minter has a payment mint, then per recipient its
+/// wallet, or its token account of the payment mint.
+pub fn distribute_proceeds<'info>(
+    minter: &MinterAccountV2,
+    amount: u64,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    let payouts = minter.payout_recipients();
+    let (source, recipients) = match minter.payment_mint {
+        Some(_) => (Some(&accounts[0]), &accounts[1..]),
+        None => (None, accounts),
+    };
+
+    let shares = split_proceeds(amount, &payouts);
+    for ((payout, share), recipient) in payouts.iter().zip(shares).zip(recipients) {
+        match (&minter.payment_mint, source) {
+            (Some(payment_mint), Some(source)) => {
+                let token = Account::<TokenAccount>::try_from(recipient)?;
+                if token.owner != payout.recipient || &token.mint != payment_mint {
+                    return Err(MinterError::InvalidPayoutAccount.into());
+                }
+                if share > 0 {
+                    token::transfer(
+                        CpiContext::new(
+                            token_program.clone(),
+                            token::Transfer {
+                                from: source.clone(),
+                                to: recipient.clone(),
+                                authority: payer.clone(),
+                            },
+                        ),
+                        share,
+                    )?;
+                }
+            }
+            _ => {
+                if recipient.key != &payout.recipient {
+                    return Err(MinterError::InvalidPayoutAccount.into());
+                }
+                if share > 0 {
+                    invoke(
+                        &system_instruction::transfer(payer.key, recipient.key, share),
+                        &[payer.clone(), recipient.clone(), system_program.clone()],
+                    )?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Pays the referrer its share of `price` and adds the mint to its
+/// ['referrer_stats', minter, referrer] account, created on first use.
+/// Returns the share paid.
+pub fn pay_referral<'info>(
+    minter: &Pubkey,
+    basis_points: u16,
+    price: u64,
+    referrer: &AccountInfo<'info>,
+    stats_info: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    rent: &AccountInfo<'info>,
+) -> Result<u64> {
+    if referrer.key == payer.key {
+        return Err(MinterError::SelfReferral.into());
+    }
+
+    if stats_info.data_is_empty() {
+        let (stats_addr, bump) = Pubkey::find_program_address(
+            &[REFERRER_STATS_KEY, minter.as_ref(), referrer.key.as_ref()],
+            &crate::ID,
+        );
+        if stats_info.key != &stats_addr {
+            return Err(MinterError::InvalidReferrerStats.into());
+        }
+        create_or_allocate_account_raw(
+            crate::ID,
+            stats_info,
+            rent,
+            system_program,
+            payer,
+            8 + ReferrerStats::LEN,
+            &[&[
+                REFERRER_STATS_KEY,
+                minter.as_ref(),
+                referrer.key.as_ref(),
+                &[bump],
+            ]],
+        )?;
+
+        let mut stats = Account::<ReferrerStats>::try_from_unchecked(stats_info)?;
+        stats.minter = *minter;
+        stats.referrer = *referrer.key;
+        stats.bump = bump;
+        stats.exit(&crate::ID)?;
+    }
+
+    let mut stats = Account::<ReferrerStats>::try_from(stats_info)?;
+    if &stats.minter != minter || &stats.referrer != referrer.key {
+        return Err(MinterError::InvalidReferrerStats.into());
+    }
+
+    let fee = (price as u128 * basis_points as u128 / MAX_BASIS_POINTS as u128) as u64;
+    if fee > 0 {
+        invoke(
+            &system_instruction::transfer(payer.key, referrer.key, fee),
+            &[payer.clone(), referrer.clone(), system_program.clone()],
+        )?;
+    }
+
+    stats.mints += 1;
+    stats.earnings += fee;
+    stats.exit(&crate::ID)?;
+    Ok(fee)
+}
+
+/// Settles a mint charged `price_paid`: takes the proceeds accounts unless
+/// the payment is `escrowed`, checks `gate` and the burn to mint, then pays
+/// the optional referrer and the proceeds. Takes from `remaining_accounts`
+/// as documented on `mint_with_collection`, after the auction or escrow
+/// accounts.
+pub fn settle_mint<'a, 'info: 'a>(
+    minter_key: &Pubkey,
+    minter: &MinterAccountV2,
+    mint: &Pubkey,
+    gate: Option<&TokenGate>,
+    price_paid: u64,
+    escrowed: bool,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    rent: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    token_metadata_program: &AccountInfo<'info>,
+    remaining_accounts: &mut std::slice::Iter<'a, AccountInfo<'info>>,
+) -> Result<()> {
+    // proceeds are paid once the referrer, if any, is known
+    let proceeds_accounts = if price_paid > 0 && !escrowed {
+        let count = minter.payout_recipients().len() + minter.payment_mint.is_some() as usize;
+        Some(
+            (0..count)
+                .map(|_| next_account_info(remaining_accounts).cloned())
+                .collect::<std::result::Result<Vec<_>, _>>()?,
+        )
+    } else {
+        None
+    };
+    emit!(MintPriceEvent {
+        minter: *minter_key,
+        mint: *mint,
+        payer: *payer.key,
+        price_paid,
+    });
+
+    if let Some(gate) = gate {
+        check_token_gate(
+            gate,
+            minter_key,
+            payer,
+            system_program,
+            rent,
+            remaining_accounts,
+        )?;
+    }
+
+    if let Some(collection) = &minter.burn_to_mint {
+        burn_for_mint(
+            collection,
+            payer,
+            token_program,
+            token_metadata_program,
+            remaining_accounts,
+        )?;
+    }
+
+    // the referrer and its stats are optional and come last, referral
+    // fees are paid in lamports only
+    let referral_base = if proceeds_accounts.is_some() && minter.payment_mint.is_none() {
+        price_paid
+    } else {
+        0
+    };
+    let referral_fee = match (minter.referral_basis_points, remaining_accounts.next()) {
+        (Some(basis_points), Some(referrer)) => pay_referral(
+            minter_key,
+            basis_points,
+            referral_base,
+            referrer,
+            next_account_info(remaining_accounts)?,
+            payer,
+            system_program,
+            rent,
+        )?,
+        _ => 0,
+    };
+    if let Some(accounts) = &proceeds_accounts {
+        distribute_proceeds(
+            minter,
+            price_paid - referral_fee,
+            payer,
+            system_program,
+            token_program,
+            accounts,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Verifies a new item in its collection, through the sized collection
+/// instruction if the collection is `sized`, which keeps its item count in
+/// the collection details.
+pub fn verify_collection_item<'info>(
+    sized: bool,
+    ctx: CpiContext<'_, '_, '_, 'info, token_metadata::VerifyCollection<'info>>,
+) -> Result<()> {
+    if !sized {
+        return token_metadata::verify_collection(ctx);
+    }
+
+    let accounts = token_metadata::VerifySizedCollectionItem {
+        metadata: ctx.accounts.metadata,
+        collection_authority: ctx.accounts.collection_authority,
+        payer: ctx.accounts.payer,
+        collection_mint: ctx.accounts.collection_mint,
+        collection: ctx.accounts.collection,
+        collection_master_edition_account: ctx.accounts.collection_master_edition_account,
+    };
+    token_metadata::verify_sized_collection_item(
+        CpiContext::new_with_signer(ctx.program, accounts, ctx.signer_seeds)
+            .with_remaining_accounts(ctx.remaining_accounts),
+    )
+}
+
+/// Flags the primary sale of paid mints, unless the minter skips it, so
+/// later sales pay royalties to all creators. The recipient has to sign.
+pub fn flag_primary_sale<'info>(
+    minter: &MinterAccountV2,
+    price_paid: u64,
+    ctx: CpiContext<'_, '_, '_, 'info, token_metadata::UpdatePrimarySaleHappenedViaToken<'info>>,
+) -> Result<()> {
+    if price_paid > 0 && !minter.skip_primary_sale {
+        // Token Metadata only takes the flag from the token owner
+        if !ctx.accounts.owner.is_signer {
+            return Err(MinterError::RecipientNotSigner.into());
+        }
+        token_metadata::update_primary_sale_happened_via_token(ctx)?;
+    }
+    Ok(())
+}
+
+/// Burns an NFT of `collection` held by the payer. Takes from
+/// `remaining_accounts`, all mut: the payer's token account, the NFT
+/// metadata, mint and master edition, and the collection metadata.
+pub fn burn_for_mint<'a, 'info: 'a>(
+    collection: &Pubkey,
+    payer: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    token_metadata_program: &AccountInfo<'info>,
+    remaining_accounts: &mut std::slice::Iter<'a, AccountInfo<'info>>,
+) -> Result<()> {
+    let token_info = next_account_info(remaining_accounts)?;
+    let metadata_info = next_account_info(remaining_accounts)?;
+    let mint_info = next_account_info(remaining_accounts)?;
+    let edition_info = next_account_info(remaining_accounts)?;
+    let collection_metadata_info = next_account_info(remaining_accounts)?;
+
+    let token = Account::<TokenAccount>::try_from(token_info)?;
+    if &token.owner != payer.key || &token.mint != mint_info.key || token.amount != 1 {
+        return Err(MinterError::InvalidBurnToken.into());
+    }
+    let metadata = Account::<token_metadata::MetadataAccount>::try_from(metadata_info)?;
+    if &metadata.mint != mint_info.key {
+        return Err(MinterError::InvalidBurnToken.into());
+    }
+    match &metadata.collection {
+        Some(c) if &c.key == collection && c.verified => {}
+        _ => return Err(MinterError::BurnCollectionMismatch.into()),
+    }
+
+    token_metadata::burn_nft(
+        CpiContext::new(
+            token_metadata_program.clone(),
+            token_metadata::BurnNft {
+                metadata: metadata_info.clone(),
+                owner: payer.clone(),
+                mint: mint_info.clone(),
+                token: token_info.clone(),
+                edition: edition_info.clone(),
+                spl_token: token_program.clone(),
+            },
+        )
+        .with_remaining_accounts(vec![collection_metadata_info.clone()]),
+    )
+}
+
+/// Draws an unused index from the pool, seeded by the most recent slot hash,
+/// the payer and the number of indices taken.
+///
+/// All of these are known when a transaction is simulated, so the draw is
+/// not secure against payers who grind for a specific index. It only keeps
+/// the order of the indices from being known in advance.
+pub fn draw_index<'info>(
+    program_id: &Pubkey,
+    minter: &Pubkey,
+    payer: &Pubkey,
+    index_pool_info: &AccountInfo<'info>,
+    slot_hashes_info: &AccountInfo<'info>,
+) -> Result<u32> {
+    if slot_hashes_info.key != &slot_hashes::ID {
+        return Err(MinterError::InvalidSlotHashes.into());
+    }
+
+    let mut pool = Account::<IndexPool>::try_from(index_pool_info)?;
+    if &pool.minter != minter {
+        return Err(MinterError::InvalidIndexPool.into());
+    }
+    let available = pool.size - pool.taken;
+    if available == 0 {
+        return Err(MinterError::IndexPoolExhausted.into());
+    }
+
+    let seed = {
+        let data = slot_hashes_info.try_borrow_data()?;
+        // skip the entry count and the slot of the most recent entry
+        keccak::hashv(&[&data[16..48], payer.as_ref(), &pool.taken.to_le_bytes()])
+    };
+    let pick = u64::from_le_bytes(seed.0[..8].try_into().unwrap()) % available as u64;
+    let index =
+        bitmap_nth_unset(&pool.bitmap, pick as u32).ok_or(MinterError::IndexPoolExhausted)?;
+
+    bitmap_set(&mut pool.bitmap, index);
+    pool.taken += 1;
+    pool.exit(program_id)?;
+
+    Ok(index as u32)
+}
+
+fn bitmap_nth_unset(bitmap: &[u8], mut n: u32) -> Option<usize> {
+    for (i, byte) in bitmap.iter().enumerate() {
+        let unset = byte.count_zeros();
+        if n >= unset {
+            n -= unset;
+            continue;
+        }
+        for bit in 0..8 {
+            if byte & (1 << bit) == 0 {
+                if n == 0 {
+                    return Some(i * 8 + bit);
+                }
+                n -= 1;
+            }
+        }
+    }
+    None
+}
+
+fn bitmap_is_set(bitmap: &[u8], index: usize) -> bool {
+    bitmap[index / 8] & (1 << (index % 8)) != 0
+}
+
+fn bitmap_set(bitmap: &mut [u8], index: usize) {
+    bitmap[index / 8] |= 1 << (index % 8);
+}
+
+/// Moves lamports out of an account owned by this program.
+pub fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let from_lamports = from
+        .lamports()
+        .checked_sub(amount)
+        .ok_or(MinterError::PaymentOverflow)?;
+    let to_lamports = to
+        .lamports()
+        .checked_add(amount)
+        .ok_or(MinterError::PaymentOverflow)?;
+    **from.try_borrow_mut_lamports()? = from_lamports;
+    **to.try_borrow_mut_lamports()? = to_lamports;
+    Ok(())
+}
+
+#[inline(always)]
+pub fn realloc_account_raw<'a>(
+    account_info: &AccountInfo<'a>,
+    payer_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    size: usize,
+) -> Result<()> {
+    if account_info.data_len() >= size {
+        return Ok(());
+    }
+
+    let required_lamports = Rent::get()?
+        .minimum_balance(size)
+        .saturating_sub(account_info.lamports());
+    if required_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(payer_info.key, account_info.key, required_lamports),
+            &[
+                payer_info.clone(),
+                account_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+
+    account_info.realloc(size, true)?;
+    Ok(())
+}
+
 #[inline(always)]
 pub fn create_or_allocate_account_raw<'a>(
     program_id: Pubkey,
@@ -672,3 +5312,125 @@ pub fn create_or_allocate_account_raw<'a>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bitmap_nth_unset_skips_set_bits() {
+        let bitmap = [0b0000_0101, 0b0000_0000];
+        assert_eq!(bitmap_nth_unset(&bitmap, 0), Some(1));
+        assert_eq!(bitmap_nth_unset(&bitmap, 1), Some(3));
+        assert_eq!(bitmap_nth_unset(&bitmap, 6), Some(8));
+        assert_eq!(bitmap_nth_unset(&bitmap, 13), Some(15));
+        assert_eq!(bitmap_nth_unset(&bitmap, 14), None);
+    }
+
+    #[test]
+    fn bitmap_nth_unset_full_bitmap() {
+        assert_eq!(bitmap_nth_unset(&[0xff, 0xff], 0), None);
+        assert_eq!(bitmap_nth_unset(&[], 0), None);
+    }
+
+    #[test]
+    fn bitmap_nth_unset_padded_last_byte() {
+        // a pool of 10 indices, bits 10..16 of the last byte are padding
+        let size = 10;
+        let mut bitmap = vec![0; RevealRecord::bitmap_len(size)];
+        for index in [0, 3, 8] {
+            bitmap_set(&mut bitmap, index);
+        }
+
+        // draw_index only asks for n below the number of indices left
+        let drawn = (0..size - 3)
+            .map(|n| bitmap_nth_unset(&bitmap, n).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(drawn, vec![1, 2, 4, 5, 6, 7, 9]);
+    }
+
+    fn auction() -> DutchAuction {
+        DutchAuction {
+            start_price: 1_000,
+            floor_price: 250,
+            start_time: 100,
+            interval: 10,
+            step: 100,
+            rebate: false,
+        }
+    }
+
+    #[test]
+    fn price_at_steps_down_per_interval() {
+        let auction = auction();
+        assert_eq!(auction.price_at(100), 1_000);
+        assert_eq!(auction.price_at(109), 1_000);
+        assert_eq!(auction.price_at(110), 900);
+        assert_eq!(auction.price_at(155), 500);
+    }
+
+    #[test]
+    fn price_at_stops_at_floor() {
+        let auction = auction();
+        assert_eq!(auction.price_at(170), 300);
+        assert_eq!(auction.price_at(180), 250);
+        assert_eq!(auction.price_at(10_000), 250);
+        assert_eq!(auction.price_at(i64::MAX), 250);
+    }
+
+    #[test]
+    fn price_at_before_start() {
+        let auction = auction();
+        assert_eq!(auction.price_at(99), 1_000);
+        assert_eq!(auction.price_at(0), 1_000);
+        assert_eq!(auction.price_at(i64::MIN), 1_000);
+    }
+
+    fn payout(basis_points: u16) -> Payout {
+        Payout {
+            recipient: Pubkey::new_unique(),
+            basis_points,
+        }
+    }
+
+    #[test]
+    fn split_proceeds_remainder_to_last() {
+        let payouts = [payout(3_333), payout(3_333), payout(3_334)];
+        assert_eq!(split_proceeds(100, &payouts), vec![33, 33, 34]);
+        assert_eq!(split_proceeds(1, &payouts), vec![0, 0, 1]);
+        assert_eq!(split_proceeds(0, &payouts), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn split_proceeds_sums_to_amount() {
+        let payouts = [payout(7_000), payout(2_999), payout(1)];
+        for amount in [1, 9, 10_001, 123_456_789, u64::MAX] {
+            let shares = split_proceeds(amount, &payouts);
+            assert_eq!(
+                shares.iter().map(|&share| share as u128).sum::<u128>(),
+                amount as u128
+            );
+        }
+        assert_eq!(
+            split_proceeds(u64::MAX, &[payout(MAX_BASIS_POINTS)]),
+            vec![u64::MAX]
+        );
+    }
+
+    #[test]
+    fn current_minter_v2_rejects_legacy_layout() {
+        // owner, u32 count and a collection, as written before layout version 1
+        let mut data = <MinterAccountV2 as anchor_lang::Discriminator>::discriminator().to_vec();
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&7u32.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+
+        let err = CurrentMinterV2::try_deserialize(&mut &data[..]).unwrap_err();
+        assert_eq!(err, MinterError::OutdatedMinterLayout.into());
+
+        let minter = MinterAccountV2::deserialize_legacy(&data).unwrap();
+        assert_eq!(minter.legacy_count, 7);
+        assert!(minter.collection.is_some());
+    }
+}
@@ -0,0 +1,275 @@
+//! Compute unit benchmark for the mint instructions.
+//!
+//! Needs the BPF builds of this program and of Token Metadata, e.g.
+//! `cp metadata.so programs/minter/tests/fixtures/ && cargo test-bpf -- --nocapture --test-threads 1`.
+//! Each mint prints its name, followed by the runtime's
+//! `Program <minter> consumed N of M compute units` log line.
+//!
+//! Before/after numbers for the stored minter bump have not been measured
+//! yet: run this once on the parent of the change (the bumps derived with
+//! `find_program_address`) and once on it, and record both here for
+//! `mint_for` and `mint_with_collection`.
+#![cfg(feature = "test-bpf")]
+
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_metadata::token_metadata;
+use anchor_spl::associated_token::get_associated_token_address;
+use solana_program_test::{BanksClient, ProgramTest};
+use solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program, sysvar,
+    transaction::Transaction,
+};
+
+fn program_test() -> ProgramTest {
+    let mut program_test = ProgramTest::new("minter", minter::id(), None);
+    program_test.add_program("metadata", token_metadata::ID, None);
+    program_test
+}
+
+fn metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"metadata", token_metadata::ID.as_ref(), mint.as_ref()],
+        &token_metadata::ID,
+    )
+    .0
+}
+
+fn master_edition_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"metadata",
+            token_metadata::ID.as_ref(),
+            mint.as_ref(),
+            b"edition",
+        ],
+        &token_metadata::ID,
+    )
+    .0
+}
+
+fn collection_authority_record_address(mint: &Pubkey, authority: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"metadata",
+            token_metadata::ID.as_ref(),
+            mint.as_ref(),
+            b"collection_authority",
+            authority.as_ref(),
+        ],
+        &token_metadata::ID,
+    )
+    .0
+}
+
+fn basic_mint(payer: &Pubkey, mint: Pubkey) -> minter::accounts::BasicMint {
+    minter::accounts::BasicMint {
+        mint,
+        recipient: *payer,
+        associated_token_account: get_associated_token_address(payer, &mint),
+        metadata: metadata_address(&mint),
+        master_edition: master_edition_address(&mint),
+        system_program: system_program::id(),
+        token_program: anchor_spl::token::ID,
+        associated_token_program: anchor_spl::associated_token::ID,
+        token_metadata_program: token_metadata::ID,
+        rent: sysvar::rent::id(),
+    }
+}
+
+async fn process(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: Hash,
+    instruction: Instruction,
+) {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_blockhash,
+    );
+    banks_client.process_transaction(transaction).await.unwrap();
+}
+
+#[tokio::test]
+async fn mint_for_compute_units() {
+    let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
+
+    let (minter, _) =
+        Pubkey::find_program_address(&[b"minter", payer.pubkey().as_ref()], &minter::id());
+    process(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        Instruction {
+            program_id: minter::id(),
+            accounts: minter::accounts::InitializeMinter {
+                authority: payer.pubkey(),
+                minter,
+                system_program: system_program::id(),
+                rent: sysvar::rent::id(),
+            }
+            .to_account_metas(None),
+            data: minter::instruction::InitializeMinter {}.data(),
+        },
+    )
+    .await;
+
+    for count in 0u64..3 {
+        let (mint, mint_bump) = Pubkey::find_program_address(
+            &[b"mint", &minter::mint_index_seed(count)],
+            &minter::id(),
+        );
+        let (mint_record, record_bump) = minter::MintRecord::address(&mint);
+        println!("mint_for #{}", count);
+        process(
+            &mut banks_client,
+            &payer,
+            recent_blockhash,
+            Instruction {
+                program_id: minter::id(),
+                accounts: minter::accounts::MintFor {
+                    payer: payer.pubkey(),
+                    minter,
+                    mint_record,
+                    basic: basic_mint(&payer.pubkey(), mint),
+                }
+                .to_account_metas(None),
+                data: minter::instruction::MintFor {
+                    mint_bump,
+                    record_bump,
+                    name: format!("Bench #{}", count),
+                    symbol: "BENCH".to_string(),
+                    uri: format!("https://example.com/{}.json", count),
+                    seller_fee_basis_points: 500,
+                    is_mutable: true,
+                }
+                .data(),
+            },
+        )
+        .await;
+    }
+}
+
+#[tokio::test]
+async fn mint_with_collection_compute_units() {
+    let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
+
+    let (minter, _) =
+        Pubkey::find_program_address(&[b"minter_v2", payer.pubkey().as_ref()], &minter::id());
+    process(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        Instruction {
+            program_id: minter::id(),
+            accounts: minter::accounts::InitializeMinterV2 {
+                authority: payer.pubkey(),
+                minter,
+                system_program: system_program::id(),
+                rent: sysvar::rent::id(),
+            }
+            .to_account_metas(None),
+            data: minter::instruction::InitializeMinterV2 {}.data(),
+        },
+    )
+    .await;
+
+    let (collection_mint, collection_mint_bump) = Pubkey::find_program_address(
+        &[b"collection_mint", minter.as_ref(), &0u32.to_le_bytes()],
+        &minter::id(),
+    );
+    let (collection_registry, _) = Pubkey::find_program_address(
+        &[
+            b"collection_registry",
+            minter.as_ref(),
+            collection_mint.as_ref(),
+        ],
+        &minter::id(),
+    );
+    let collection_authority_record =
+        collection_authority_record_address(&collection_mint, &minter);
+    process(
+        &mut banks_client,
+        &payer,
+        recent_blockhash,
+        Instruction {
+            program_id: minter::id(),
+            accounts: minter::accounts::CreateCollection {
+                authority: payer.pubkey(),
+                minter,
+                mint: collection_mint,
+                associated_token_account: get_associated_token_address(
+                    &payer.pubkey(),
+                    &collection_mint,
+                ),
+                metadata: metadata_address(&collection_mint),
+                master_edition: master_edition_address(&collection_mint),
+                collection_authority_record,
+                collection_registry,
+                system_program: system_program::id(),
+                token_program: anchor_spl::token::ID,
+                associated_token_program: anchor_spl::associated_token::ID,
+                token_metadata_program: token_metadata::ID,
+                rent: sysvar::rent::id(),
+            }
+            .to_account_metas(None),
+            data: minter::instruction::CreateCollection {
+                id: 0,
+                mint_bump: collection_mint_bump,
+                name: "Bench".to_string(),
+                symbol: "BENCH".to_string(),
+                uri: "https://example.com/collection.json".to_string(),
+                size: None,
+                minter_update_authority: true,
+            }
+            .data(),
+        },
+    )
+    .await;
+
+    for count in 0u64..3 {
+        let (mint, mint_bump) = Pubkey::find_program_address(
+            &[b"mint_v2", &minter::mint_index_seed(count)],
+            &minter::id(),
+        );
+        let (mint_record, record_bump) = minter::MintRecord::address(&mint);
+        println!("mint_with_collection #{}", count);
+        process(
+            &mut banks_client,
+            &payer,
+            recent_blockhash,
+            Instruction {
+                program_id: minter::id(),
+                accounts: minter::accounts::MintWithCollection {
+                    payer: payer.pubkey(),
+                    minter,
+                    mint_record,
+                    collection_registry,
+                    collection_authority_record,
+                    collection_mint,
+                    collection: metadata_address(&collection_mint),
+                    collection_master_edition_account: master_edition_address(&collection_mint),
+                    basic: basic_mint(&payer.pubkey(), mint),
+                }
+                .to_account_metas(None),
+                data: minter::instruction::MintWithCollection {
+                    mint_bump,
+                    record_bump,
+                    name: format!("Bench #{}", count),
+                    symbol: "BENCH".to_string(),
+                    uri: format!("https://example.com/{}.json", count),
+                    seller_fee_basis_points: 500,
+                    is_mutable: true,
+                    collection: collection_mint,
+                }
+                .data(),
+            },
+        )
+        .await;
+    }
+}